@@ -0,0 +1,163 @@
+use pdf::PdfError;
+use std::{error::Error, fmt::Display};
+
+/// Everything that can go wrong while extracting and writing images
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum VortexError {
+    /// The document or one of its objects could not be parsed
+    Parse(PdfError),
+    /// The 1-based `page` or the objects it uses could not be read
+    Page { page: u32, source: Box<VortexError> },
+    /// An image failed to decode. `page` is numbered from 1 and `object_id` is `None` for
+    /// inline images
+    Decode {
+        page: Option<u32>,
+        object_id: Option<u64>,
+        source: Box<VortexError>,
+    },
+    /// A decoded image failed to convert, encode or be written, numbered like [`Decode`]
+    ///
+    /// [`Decode`]: VortexError::Decode
+    Write {
+        page: Option<u32>,
+        object_id: Option<u64>,
+        source: Box<VortexError>,
+    },
+    /// The image stream is compressed with a codec that cannot be decoded
+    UnsupportedFilter(&'static str),
+    /// The color space of an image has no conversion to RGB
+    UnsupportedColorSpace(String),
+    /// The samples of an image use a bit depth that cannot be unpacked
+    UnsupportedBitDepth(i32),
+    /// The image data does not match its dictionary
+    InvalidImage(&'static str),
+    /// The output image could not be encoded
+    Encode(Box<dyn Error + Send + Sync>),
+    /// Reading the document or writing an image failed
+    Io(std::io::Error),
+    /// An output format or one of its encoder options is invalid
+    InvalidFormat(String),
+    /// A page range is invalid
+    InvalidPageRange(String),
+    /// An output file name template is invalid
+    InvalidNameTemplate(String),
+}
+
+impl VortexError {
+    pub(crate) fn decode(page: Option<u32>, object_id: Option<u64>, source: VortexError) -> Self {
+        VortexError::Decode {
+            page,
+            object_id,
+            source: Box::new(source),
+        }
+    }
+
+    pub(crate) fn write(page: Option<u32>, object_id: Option<u64>, source: VortexError) -> Self {
+        VortexError::Write {
+            page,
+            object_id,
+            source: Box::new(source),
+        }
+    }
+
+    /// `format` output asked of a build without the cargo `feature` that provides it
+    pub(crate) fn missing_feature(format: &str, feature: &str) -> Self {
+        VortexError::InvalidFormat(format!(
+            "{format} output requires vortex to be built with the {feature} feature"
+        ))
+    }
+}
+
+/// ` 12 on page 3`, either part may be missing
+fn write_origin(
+    f: &mut std::fmt::Formatter<'_>,
+    page: &Option<u32>,
+    object_id: &Option<u64>,
+) -> std::fmt::Result {
+    if let Some(object_id) = object_id {
+        write!(f, " {object_id}")?;
+    }
+    if let Some(page) = page {
+        write!(f, " on page {page}")?;
+    }
+    Ok(())
+}
+
+impl Display for VortexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use VortexError::*;
+
+        match self {
+            Parse(e) => write!(f, "Failed to parse pdf: {e}"),
+            Page { page, .. } => write!(f, "Failed to read page {page}"),
+            Decode {
+                page, object_id, ..
+            } => {
+                f.write_str("Failed to decode image")?;
+                write_origin(f, page, object_id)
+            }
+            Write {
+                page, object_id, ..
+            } => {
+                f.write_str("Failed to write image")?;
+                write_origin(f, page, object_id)
+            }
+            UnsupportedFilter(filter) => write!(f, "Unsupported filter {filter}"),
+            UnsupportedColorSpace(msg) => write!(f, "Unsupported color space: {msg}"),
+            UnsupportedBitDepth(bpc) => write!(f, "Unsupported bits per component {bpc}"),
+            InvalidImage(msg) => f.write_str(msg),
+            Encode(e) => write!(f, "Failed to encode image: {e}"),
+            Io(e) => write!(f, "{e}"),
+            InvalidFormat(msg) => f.write_str(msg),
+            InvalidPageRange(msg) => f.write_str(msg),
+            InvalidNameTemplate(msg) => f.write_str(msg),
+        }
+    }
+}
+
+impl Error for VortexError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        use VortexError::*;
+
+        match self {
+            Parse(e) => Some(e),
+            Page { source, .. } | Decode { source, .. } | Write { source, .. } => {
+                Some(source.as_ref())
+            }
+            Encode(e) => Some(e.as_ref()),
+            Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<PdfError> for VortexError {
+    fn from(value: PdfError) -> Self {
+        VortexError::Parse(value)
+    }
+}
+
+impl From<std::io::Error> for VortexError {
+    fn from(value: std::io::Error) -> Self {
+        VortexError::Io(value)
+    }
+}
+
+impl From<image::ImageError> for VortexError {
+    fn from(value: image::ImageError) -> Self {
+        match value {
+            image::ImageError::IoError(e) => VortexError::Io(e),
+            e => VortexError::Encode(Box::new(e)),
+        }
+    }
+}
+
+impl From<tiff::TiffError> for VortexError {
+    fn from(value: tiff::TiffError) -> Self {
+        match value {
+            tiff::TiffError::IoError(e) => VortexError::Io(e),
+            e => VortexError::Encode(Box::new(e)),
+        }
+    }
+}
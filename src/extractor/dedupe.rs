@@ -0,0 +1,171 @@
+use crate::{Mask, RawImage};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+
+/// Which copies of an image are dropped so that it is extracted once
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Dedupe {
+    /// Extract every occurrence of every image
+    #[default]
+    Off,
+    /// Extract an XObject used on several pages once
+    Reference,
+    /// Also drop images whose decoded pixels match an image already extracted
+    Content,
+}
+
+type Hash = [u8; 32];
+
+impl Dedupe {
+    /// Hash of the pixels of `image` when copies are found by content
+    pub(crate) fn hash(self, image: &RawImage) -> Option<Hash> {
+        (self == Dedupe::Content).then(|| content_hash(image))
+    }
+}
+
+/// Image extracted once for all of its copies
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Duplicate {
+    /// 1-based page of the extracted copy
+    pub page: u32,
+    /// Position of the extracted copy on its page
+    pub page_index: usize,
+    /// Every page the image appears on, in order
+    pub pages: Vec<u32>,
+}
+
+/// Images kept so far, by the page and position of their extracted copy
+#[derive(Default)]
+pub(crate) struct Seen {
+    mode: Dedupe,
+    references: HashMap<u64, (u32, usize)>,
+    hashes: HashMap<Hash, (u32, usize)>,
+    /// Position in the report of the images found to have copies
+    copies: HashMap<(u32, usize), usize>,
+}
+
+impl Seen {
+    pub(crate) fn new(mode: Dedupe) -> Self {
+        Seen {
+            mode,
+            ..Default::default()
+        }
+    }
+
+    /// Whether the object `object_id` was met before, without recording anything
+    pub(crate) fn knows(&self, object_id: u64) -> bool {
+        self.references.contains_key(&object_id)
+    }
+
+    /// Whether the object `object_id` was met before, the page is added to `duplicates`
+    /// when it was. Checked before decoding, so copies of a broken image are not retried.
+    pub(crate) fn is_reference_copy(
+        &mut self,
+        object_id: Option<u64>,
+        page: u32,
+        page_index: usize,
+        duplicates: &mut Vec<Duplicate>,
+    ) -> bool {
+        let id = match object_id {
+            Some(id) if self.mode != Dedupe::Off => id,
+            _ => return false,
+        };
+
+        match self.references.get(&id) {
+            Some(&first) => {
+                self.record(first, page, duplicates);
+                true
+            }
+            None => {
+                self.references.insert(id, (page, page_index));
+                false
+            }
+        }
+    }
+
+    /// Whether the pixels of `image`, hashed to `hash`, match an image kept before
+    pub(crate) fn is_content_copy(
+        &mut self,
+        image: &RawImage,
+        hash: Option<Hash>,
+        duplicates: &mut Vec<Duplicate>,
+    ) -> bool {
+        let hash = match hash {
+            Some(hash) => hash,
+            None => return false,
+        };
+
+        let page = image.page.unwrap_or_default();
+
+        match self.hashes.get(&hash) {
+            Some(&first) => {
+                // Later uses of this object are copies of the same extracted image
+                if let Some(id) = image.object_id {
+                    self.references.insert(id, first);
+                }
+
+                self.record(first, page, duplicates);
+                true
+            }
+            None => {
+                self.hashes.insert(hash, (page, image.page_index));
+                false
+            }
+        }
+    }
+
+    fn record(&mut self, first: (u32, usize), page: u32, duplicates: &mut Vec<Duplicate>) {
+        let i = *self.copies.entry(first).or_insert_with(|| {
+            duplicates.push(Duplicate {
+                page: first.0,
+                page_index: first.1,
+                pages: vec![first.0],
+            });
+            duplicates.len() - 1
+        });
+
+        // Copies are met in page order
+        let pages = &mut duplicates[i].pages;
+        if pages.last() != Some(&page) {
+            pages.push(page);
+        }
+    }
+}
+
+/// SHA-256 of what ends up in the output file: the dimensions, colors and pixels of the
+/// image and its mask. Undecoded images are hashed by their stream.
+fn content_hash(image: &RawImage) -> Hash {
+    let mut hasher = Sha256::new();
+    update(&mut hasher, image);
+    hasher.finalize().into()
+}
+
+fn update(hasher: &mut Sha256, image: &RawImage) {
+    let dict = &image.image_dict;
+
+    hasher.update(
+        format!(
+            "{}x{} {:?} {:?} {:?}",
+            dict.width, dict.height, dict.bits_per_component, dict.color_space, dict.decode
+        )
+        .as_bytes(),
+    );
+
+    match image.encoded {
+        Some(ref encoded) if image.is_empty() => hasher.update(&encoded.data),
+        _ => hasher.update(&**image),
+    }
+
+    match image.mask {
+        Some(Mask::Soft(ref mask)) => {
+            hasher.update(b"soft");
+            update(hasher, mask);
+        }
+        Some(Mask::Stencil(ref mask)) => {
+            hasher.update(b"stencil");
+            update(hasher, mask);
+        }
+        Some(Mask::ColorKey(ref ranges)) => hasher.update(format!("{ranges:?}").as_bytes()),
+        None => {}
+    }
+}
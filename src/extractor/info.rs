@@ -0,0 +1,91 @@
+use super::{get_page_images, open, ExtractOptions, Method};
+use crate::manifest::{color_space_name, filter_name};
+use crate::{Result, VortexError};
+use serde::Serialize;
+
+/// What is known about an image from its dictionary, without decoding it
+#[derive(Clone, Debug, Serialize)]
+pub struct ImageInfo {
+    /// 1-based page the image was found on
+    pub page: u32,
+    /// Resource name of the XObject, `None` for inline images
+    pub xobject: Option<String>,
+    pub object_id: Option<u64>,
+    pub width: u32,
+    pub height: u32,
+    pub bits_per_component: Option<i32>,
+    pub color_space: Option<String>,
+    /// Filters of the stream, the image codec comes last
+    pub filters: Vec<String>,
+    /// Size of the stream as stored in the pdf, before any filter is applied
+    pub encoded_size: usize,
+}
+
+/// Describe every image of the selected pages without decoding their pixels
+pub fn list_images(method: Method, options: &ExtractOptions) -> Result<Vec<ImageInfo>> {
+    let file = open(method)?;
+    let num_pages = file.num_pages();
+
+    let pages = options.page_indices(num_pages)?;
+
+    let mut infos = vec![];
+
+    for index in pages {
+        let page = index + 1;
+
+        let images = match file
+            .get_page(index)
+            .map_err(VortexError::from)
+            .and_then(|p| get_page_images(&p, &file))
+        {
+            Ok(images) => images,
+            Err(e) => {
+                let e = VortexError::Page {
+                    page,
+                    source: Box::new(e),
+                };
+                if !options.lenient {
+                    return Err(e);
+                }
+                log::warn!("skipping : {e}");
+                continue;
+            }
+        };
+
+        for pending in images {
+            let object_id = pending.object_id();
+
+            let info = pending.resolve(&file).and_then(|resolved| {
+                let img = match resolved.image() {
+                    Some(img) => img,
+                    None => return Ok(None),
+                };
+
+                Ok(Some(ImageInfo {
+                    page,
+                    xobject: pending.name().map(str::to_owned),
+                    object_id,
+                    width: img.width,
+                    height: img.height,
+                    bits_per_component: img.bits_per_component,
+                    color_space: img.color_space.as_ref().map(color_space_name),
+                    filters: img.inner.info.filters.iter().map(filter_name).collect(),
+                    encoded_size: img.inner.len(),
+                }))
+            });
+
+            match info {
+                Ok(info) => infos.extend(info),
+                Err(e) if options.lenient => {
+                    log::warn!(
+                        "skipping : {}",
+                        VortexError::decode(Some(page), object_id, e)
+                    )
+                }
+                Err(e) => return Err(VortexError::decode(Some(page), object_id, e)),
+            }
+        }
+    }
+
+    Ok(infos)
+}
@@ -1,21 +1,172 @@
-use crate::RawImage;
-use crate::Result;
+mod dedupe;
+mod info;
+mod parallel;
+mod range;
+
+use crate::{Encoded, Mask, RawImage, Result, VortexError};
+use dedupe::Seen;
 
 use pdf::any::AnySync;
 use pdf::backend::Backend;
+use pdf::content::Op;
+use pdf::enc::StreamFilter;
 use pdf::file::Cache;
 use pdf::file::File;
 use pdf::file::FileOptions;
+use pdf::file::NoCache;
 use pdf::object::PageRc;
-use pdf::object::{Resolve, XObject};
+use pdf::object::{
+    ImageDict, ImageXObject, PlainRef, RcRef, Ref, Resolve, Resources, Stream, XObject,
+};
+use pdf::primitive::{Name, Primitive};
 use pdf::PdfError;
+use std::borrow::Cow;
+use std::collections::{HashSet, VecDeque};
+use std::io::Read;
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::sync::Arc;
 
+pub use dedupe::{Dedupe, Duplicate};
+pub use info::{list_images, ImageInfo};
+pub use parallel::{par_extract_images, par_for_each_image};
+pub use range::PageRange;
+
 pub enum Method<'a> {
     File(PathBuf),
     Bytes(&'a [u8]),
+    Owned(Vec<u8>),
+    Shared(Arc<[u8]>),
+    /// Any reader, e.g. stdin. The document is read to the end before parsing
+    Reader(Box<dyn Read + 'a>),
+}
+
+impl<'a> From<&'a [u8]> for Method<'a> {
+    fn from(value: &'a [u8]) -> Self {
+        Method::Bytes(value)
+    }
+}
+
+impl From<Vec<u8>> for Method<'_> {
+    fn from(value: Vec<u8>) -> Self {
+        Method::Owned(value)
+    }
+}
+
+impl From<Arc<[u8]>> for Method<'_> {
+    fn from(value: Arc<[u8]>) -> Self {
+        Method::Shared(value)
+    }
+}
+
+impl From<PathBuf> for Method<'_> {
+    fn from(value: PathBuf) -> Self {
+        Method::File(value)
+    }
+}
+
+/// In memory pdf document, either borrowed from the caller or owned by the parser
+pub enum Buffer<'a> {
+    Borrowed(&'a [u8]),
+    Owned(Vec<u8>),
+    Shared(Arc<[u8]>),
+}
+
+impl Deref for Buffer<'_> {
+    type Target = [u8];
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Buffer::Borrowed(bytes) => bytes,
+            Buffer::Owned(bytes) => bytes,
+            Buffer::Shared(bytes) => bytes,
+        }
+    }
+}
+
+/// Parsed document. Nothing is cached, a cached stream would be the decoded pixels of an
+/// image and would stay in memory until the whole document is done.
+pub type Document<'a> = File<Buffer<'a>, NoCache, NoCache>;
+
+/// Knobs controlling which parts of a document are extracted
+#[derive(Clone, Debug, Default)]
+pub struct ExtractOptions {
+    /// Pages to extract from, every page when `None`
+    pub pages: Option<PageRange>,
+    /// Keep DCT and JPX streams as they are in the document instead of decoding them
+    pub raw: bool,
+    /// Keep JPEG streams without a mask or `Decode` array as they are instead of decoding
+    /// them, for [`ImageFormat::Auto`] which copies those. They are then written as JPEG
+    /// whatever the format.
+    ///
+    /// [`ImageFormat::Auto`]: crate::ImageFormat::Auto
+    pub copy_jpeg: bool,
+    /// Skip pages and images that fail to decode instead of stopping, the failures are
+    /// collected in an [`ExtractReport`]
+    pub lenient: bool,
+    /// Extract images used several times once, the pages of their copies are listed in
+    /// the [`ExtractReport`]
+    pub dedupe: Dedupe,
+}
+
+impl ExtractOptions {
+    /// Sorted 0-based indices of the pages to extract from a document with `num_pages` pages
+    pub fn page_indices(&self, num_pages: u32) -> Result<Vec<u32>> {
+        match self.pages {
+            Some(ref range) => range.indices(num_pages),
+            None => Ok((0..num_pages).collect()),
+        }
+    }
+}
+
+/// Outcome of an extraction, only lenient extraction gets past failures
+#[derive(Debug, Default)]
+pub struct ExtractReport {
+    /// Number of images extracted
+    pub extracted: usize,
+    /// Pages and images that were skipped, as [`VortexError::Page`] and
+    /// [`VortexError::Decode`] errors or the [`VortexError::Write`] errors of a
+    /// [`par_for_each_image`] callback
+    pub failures: Vec<VortexError>,
+    /// Images whose copies were dropped, with every page they appear on
+    pub duplicates: Vec<Duplicate>,
+}
+
+impl ExtractReport {
+    /// Record `e` and carry on in lenient mode, otherwise hand it back
+    fn record(&mut self, e: VortexError, lenient: bool) -> Result<()> {
+        if !lenient {
+            return Err(e);
+        }
+
+        log::warn!("skipping : {e}");
+        self.failures.push(e);
+        Ok(())
+    }
+
+    /// Every page the image extracted from `page_index` on `page` appears on
+    pub fn pages_of(&self, page: u32, page_index: usize) -> Vec<u32> {
+        self.duplicates
+            .iter()
+            .find(|d| d.page == page && d.page_index == page_index)
+            .map_or_else(|| vec![page], |d| d.pages.clone())
+    }
+}
+
+/// Load the whole document described by `method` into memory and parse it
+pub fn open(method: Method) -> Result<Document> {
+    let buffer = match method {
+        Method::File(path) => Buffer::Owned(std::fs::read(path)?),
+        Method::Bytes(bytes) => Buffer::Borrowed(bytes),
+        Method::Owned(bytes) => Buffer::Owned(bytes),
+        Method::Shared(bytes) => Buffer::Shared(bytes),
+        Method::Reader(mut reader) => {
+            let mut bytes = vec![];
+            reader.read_to_end(&mut bytes)?;
+            Buffer::Owned(bytes)
+        }
+    };
+
+    Ok(FileOptions::uncached().load(buffer)?)
 }
 
 pub fn get_pages<T, K, Y>(file: &File<T, K, Y>) -> Result<Vec<PageRc>>
@@ -26,66 +177,445 @@ where
 {
     Ok(file
         .pages()
-        .map(|page| page.unwrap())
-        .collect::<Vec<PageRc>>())
+        .collect::<std::result::Result<Vec<PageRc>, _>>()?)
 }
 
-pub fn get_raw_images<T, K, Y>(page: PageRc, file: &File<T, K, Y>) -> Result<Vec<RawImage>>
+/// Image found while walking a page, not decoded yet
+#[derive(Clone)]
+enum PendingImage {
+    /// Image XObject referenced by name from a resource dictionary
+    XObject(Name, RcRef<XObject>),
+    /// XObject that failed to load while walking the page, resolved again when decoded
+    /// so the failure is reported against the image rather than the whole page
+    Unresolved(Name, Ref<XObject>),
+    /// Image embedded in a content stream between `BI` and `EI`
+    Inline(Arc<ImageXObject>),
+}
+
+impl PendingImage {
+    fn image(&self) -> Option<&ImageXObject> {
+        match self {
+            PendingImage::XObject(_, xobject) => match **xobject {
+                XObject::Image(ref im) => Some(im),
+                _ => None,
+            },
+            PendingImage::Unresolved(..) => None,
+            PendingImage::Inline(im) => Some(im),
+        }
+    }
+
+    /// Load an XObject that failed to load during the page walk
+    fn resolve<T, K, Y>(&self, file: &File<T, K, Y>) -> Result<Cow<'_, PendingImage>>
+    where
+        T: Backend,
+        K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
+        Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
+    {
+        Ok(match self {
+            PendingImage::Unresolved(name, r) => {
+                Cow::Owned(PendingImage::XObject(name.clone(), file.get(*r)?))
+            }
+            pending => Cow::Borrowed(pending),
+        })
+    }
+
+    /// Resource name of the image, inline images have none
+    fn name(&self) -> Option<&str> {
+        match self {
+            PendingImage::XObject(name, _) | PendingImage::Unresolved(name, _) => {
+                Some(name.as_str())
+            }
+            PendingImage::Inline(_) => None,
+        }
+    }
+
+    /// Object number of the image, inline images have none
+    fn object_id(&self) -> Option<u64> {
+        match self {
+            PendingImage::XObject(_, xobject) => Some(xobject.get_ref().get_inner().id),
+            PendingImage::Unresolved(_, r) => Some(r.get_inner().id),
+            PendingImage::Inline(_) => None,
+        }
+    }
+}
+
+/// Collect the images used by the page without decoding them.
+///
+/// Form XObjects are walked recursively since many producers wrap every figure
+/// in one. Each object is visited once per page which also breaks reference cycles.
+/// Inline images are parsed out of the page and form content streams.
+fn get_page_images<T, K, Y>(page: &PageRc, file: &File<T, K, Y>) -> Result<Vec<PendingImage>>
 where
     T: Backend,
     K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
     Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
 {
     let mut images = vec![];
+    let mut visited = HashSet::new();
 
-    let resources = page.resources()?;
+    collect_xobject_images(page.resources()?, file, &mut visited, &mut images)?;
 
-    images.extend(
-        resources
-            .xobjects
-            .iter()
-            .map(|(_name, &r)| file.get(r).unwrap())
-            .filter(|o| matches!(**o, pdf::object::XObject::Image(_))),
-    );
+    if let Some(ref contents) = page.contents {
+        collect_inline_images(contents.operations(file), &mut images);
+    }
 
-    log::debug!("main : total images {}", images.len());
+    Ok(images)
+}
 
-    let mut raw_images = vec![];
+fn collect_xobject_images<T, K, Y>(
+    resources: &Resources,
+    file: &File<T, K, Y>,
+    visited: &mut HashSet<PlainRef>,
+    images: &mut Vec<PendingImage>,
+) -> Result<()>
+where
+    T: Backend,
+    K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
+    Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let mut xobjects = resources.xobjects.iter().collect::<Vec<_>>();
+    xobjects.sort_by(|(a, _), (b, _)| a.as_str().cmp(b.as_str()));
 
-    for o in images.iter() {
-        let img = match **o {
-            XObject::Image(ref im) => im,
-            _ => continue,
+    for (name, &r) in xobjects {
+        if !visited.insert(r.get_inner()) {
+            log::debug!("skipping already visited xobject {name}");
+            continue;
+        }
+
+        let xobject = match file.get(r) {
+            Ok(xobject) => xobject,
+            Err(e) => {
+                log::debug!("failed to load xobject {name} : {e}");
+                images.push(PendingImage::Unresolved(name.clone(), r));
+                continue;
+            }
         };
 
-        let data = img.image_data(file)?;
+        match *xobject {
+            XObject::Image(_) => images.push(PendingImage::XObject(name.clone(), xobject)),
+            XObject::Form(ref form) => {
+                if let Some(ref resources) = form.dict().resources {
+                    collect_xobject_images(resources, file, visited, images)?;
+                }
+
+                collect_inline_images(form.operations(file), images);
+            }
+            _ => {}
+        }
+    }
 
-        let img_dict = img.deref().to_owned();
+    Ok(())
+}
+
+/// Content streams are only parsed for their inline images, one that fails to parse is
+/// skipped rather than losing the XObject images next to it
+fn collect_inline_images(
+    ops: std::result::Result<Vec<Op>, PdfError>,
+    images: &mut Vec<PendingImage>,
+) {
+    let ops = match ops {
+        Ok(ops) => ops,
+        Err(e) => {
+            log::warn!("skipping inline images of a content stream : {e}");
+            return;
+        }
+    };
+
+    images.extend(ops.iter().filter_map(|op| match op {
+        Op::InlineImage { image } => Some(PendingImage::Inline(image.clone())),
+        _ => None,
+    }));
+}
 
-        let img = RawImage::new(&data, img_dict);
+/// Decode a pending image, errors carry the 1-based `page` and the object number.
+/// `page_index` is the position of the image in the walk of its page.
+fn decode_image<T, K, Y>(
+    pending: &PendingImage,
+    page: Option<u32>,
+    page_index: usize,
+    file: &File<T, K, Y>,
+    options: &ExtractOptions,
+) -> Result<Option<RawImage>>
+where
+    T: Backend,
+    K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
+    Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let object_id = pending.object_id();
 
-        raw_images.push(img)
+    match decode_pending(pending, file, options) {
+        Ok(img) => Ok(img.map(|img| {
+            img.with_origin(
+                page,
+                page_index,
+                pending.name().map(str::to_owned),
+                object_id,
+            )
+        })),
+        Err(e) => Err(VortexError::decode(page, object_id, e)),
     }
-    Ok(raw_images)
 }
 
-pub fn extract_images(method: Method) -> Result<Vec<RawImage>> {
-    let (pages, file) = match method {
-        Method::File(path) => {
-            let file = FileOptions::cached().open(path)?;
+fn decode_pending<T, K, Y>(
+    pending: &PendingImage,
+    file: &File<T, K, Y>,
+    options: &ExtractOptions,
+) -> Result<Option<RawImage>>
+where
+    T: Backend,
+    K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
+    Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let pending = pending.resolve(file)?;
+    let img = match pending.image() {
+        Some(im) => im,
+        None => return Ok(None),
+    };
+
+    let filters = &img.inner.info.filters;
+    let plain_jpeg = matches!(filters.last(), Some(StreamFilter::DCTDecode(_)))
+        && img.smask.is_none()
+        && img.mask.is_none()
+        && img.decode.is_none();
 
-            (get_pages(&file)?, file)
+    // Streams written as they are skip decoding altogether
+    if options.raw || (options.copy_jpeg && plain_jpeg) {
+        if let Some(encoded) = get_encoded(img, file)? {
+            let img_dict = img.deref().to_owned();
+            let filters = img.inner.info.filters.clone();
+
+            return Ok(Some(
+                RawImage::from_encoded(encoded, img_dict).with_filters(filters),
+            ));
         }
-        Method::Bytes(_bytes) => {
-            todo!("Parse files in network")
+    }
+
+    let mask = get_mask(img, file)?;
+
+    Ok(Some(decode_image_xobject(img, file)?.with_mask(mask)))
+}
+
+/// The image stream with only the generic filters removed, when what remains can be
+/// written out as a standalone file
+fn get_encoded<T, K, Y>(img: &ImageXObject, file: &File<T, K, Y>) -> Result<Option<Encoded>>
+where
+    T: Backend,
+    K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
+    Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let (data, filter) = img.raw_image_data(file)?;
+
+    let encoded = filter.map(|filter| Encoded {
+        filter: filter.clone(),
+        data,
+    });
+
+    Ok(encoded.filter(|encoded| encoded.extension().is_some()))
+}
+
+fn decode_image_xobject<T, K, Y>(img: &ImageXObject, file: &File<T, K, Y>) -> Result<RawImage>
+where
+    T: Backend,
+    K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
+    Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let data = match img.image_data(file) {
+        Ok(data) => data,
+        // pdf only decodes JPEG 2000 with an optional codec, raw mode can still copy these
+        Err(e) if matches!(img.inner.info.filters.last(), Some(StreamFilter::JPXDecode)) => {
+            log::debug!("JPX image failed to decode : {e}");
+            return Err(VortexError::UnsupportedFilter("JPXDecode"));
         }
+        Err(e) => return Err(e.into()),
     };
 
-    let mut images: Vec<RawImage> = vec![];
+    let img_dict = img.deref().to_owned();
 
-    for page in pages {
-        images.extend(get_raw_images(page, &file)?);
+    Ok(RawImage::new(&data, img_dict).with_filters(img.inner.info.filters.clone()))
+}
+
+/// Resolve and decode the `SMask` or `Mask` of an image, a soft mask wins when both are present
+fn get_mask<T, K, Y>(img: &ImageXObject, file: &File<T, K, Y>) -> Result<Option<Mask>>
+where
+    T: Backend,
+    K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
+    Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let decode_stream = |r: Ref<Stream<ImageDict>>| -> Result<Box<RawImage>> {
+        let stream = file.get(r)?;
+        let mask = ImageXObject {
+            inner: (*stream).clone(),
+        };
+        Ok(Box::new(decode_image_xobject(&mask, file)?))
+    };
+
+    if let Some(smask) = img.smask {
+        return Ok(Some(Mask::Soft(decode_stream(smask)?)));
     }
 
-    Ok(images)
+    Ok(match img.mask {
+        Some(Primitive::Reference(r)) => Some(Mask::Stencil(decode_stream(Ref::new(r))?)),
+        Some(Primitive::Array(ref ranges)) => Some(Mask::ColorKey(
+            ranges
+                .iter()
+                .map(|p| p.as_integer().map(|v| v.max(0) as u32))
+                .collect::<std::result::Result<_, _>>()?,
+        )),
+        _ => None,
+    })
+}
+
+pub fn get_raw_images<T, K, Y>(page: PageRc, file: &File<T, K, Y>) -> Result<Vec<RawImage>>
+where
+    T: Backend,
+    K: Cache<std::result::Result<AnySync, Arc<PdfError>>>,
+    Y: Cache<std::result::Result<Arc<[u8]>, Arc<PdfError>>>,
+{
+    let images = get_page_images(&page, file)?;
+
+    log::debug!("main : total images {}", images.len());
+
+    let mut raw_images = vec![];
+
+    for (i, o) in images.iter().enumerate() {
+        raw_images.extend(decode_image(o, None, i, file, &ExtractOptions::default())?);
+    }
+    Ok(raw_images)
+}
+
+/// Lazily walks the pages of a document and decodes one image per call to `next`.
+///
+/// Only the undecoded images of the current page are held in memory, so peak memory is
+/// bounded by the largest single decoded image rather than the whole document.
+pub struct ImageIter<'a> {
+    file: Document<'a>,
+    pages: std::vec::IntoIter<u32>,
+    /// Images of the current page with their 1-based page number and position on the page
+    pending: VecDeque<(u32, usize, PendingImage)>,
+    options: ExtractOptions,
+    report: ExtractReport,
+    seen: Seen,
+}
+
+impl<'a> ImageIter<'a> {
+    pub fn new(method: Method<'a>) -> Result<Self> {
+        Self::with_options(method, &ExtractOptions::default())
+    }
+
+    pub fn with_options(method: Method<'a>, options: &ExtractOptions) -> Result<Self> {
+        let file = open(method)?;
+        let num_pages = file.num_pages();
+
+        let pages = options.page_indices(num_pages)?.into_iter();
+
+        Ok(Self {
+            file,
+            pages,
+            pending: VecDeque::new(),
+            options: options.clone(),
+            report: ExtractReport::default(),
+            seen: Seen::new(options.dedupe),
+        })
+    }
+
+    /// Images extracted and failures skipped so far
+    pub fn report(&self) -> &ExtractReport {
+        &self.report
+    }
+
+    pub fn into_report(self) -> ExtractReport {
+        self.report
+    }
+
+    /// Hand `e` back to the caller, or record it and carry on in lenient mode
+    fn fail(&mut self, e: VortexError) -> Option<VortexError> {
+        self.report.record(e, self.options.lenient).err()
+    }
+
+    fn queue_page(&mut self, index: u32) -> Result<()> {
+        let page = self.file.get_page(index)?;
+
+        let images = get_page_images(&page, &self.file)?;
+
+        log::debug!("page {index} : total images {}", images.len());
+
+        self.pending.extend(
+            images
+                .into_iter()
+                .enumerate()
+                .map(|(i, image)| (index + 1, i, image)),
+        );
+
+        Ok(())
+    }
+}
+
+impl Iterator for ImageIter<'_> {
+    type Item = Result<RawImage>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((page, i, pending)) = self.pending.pop_front() {
+                let duplicates = &mut self.report.duplicates;
+
+                if self
+                    .seen
+                    .is_reference_copy(pending.object_id(), page, i, duplicates)
+                {
+                    continue;
+                }
+
+                match decode_image(&pending, Some(page), i, &self.file, &self.options) {
+                    Ok(Some(img)) => {
+                        let hash = self.options.dedupe.hash(&img);
+                        let duplicates = &mut self.report.duplicates;
+
+                        if self.seen.is_content_copy(&img, hash, duplicates) {
+                            continue;
+                        }
+
+                        self.report.extracted += 1;
+                        return Some(Ok(img));
+                    }
+                    Ok(None) => continue,
+                    Err(e) => match self.fail(e) {
+                        Some(e) => return Some(Err(e)),
+                        None => continue,
+                    },
+                }
+            }
+
+            let index = self.pages.next()?;
+
+            if let Err(e) = self.queue_page(index) {
+                let e = VortexError::Page {
+                    page: index + 1,
+                    source: Box::new(e),
+                };
+
+                if let Some(e) = self.fail(e) {
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+pub fn extract_images(method: Method) -> Result<Vec<RawImage>> {
+    ImageIter::new(method)?.collect()
+}
+
+pub fn extract_images_with(method: Method, options: &ExtractOptions) -> Result<Vec<RawImage>> {
+    ImageIter::with_options(method, options)?.collect()
+}
+
+/// Extract images along with the report of what was skipped, meant for lenient extraction
+pub fn extract_images_with_report(
+    method: Method,
+    options: &ExtractOptions,
+) -> Result<(Vec<RawImage>, ExtractReport)> {
+    let mut iter = ImageIter::with_options(method, options)?;
+    let images = iter.by_ref().collect::<Result<Vec<_>>>()?;
+
+    Ok((images, iter.into_report()))
 }
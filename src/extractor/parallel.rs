@@ -0,0 +1,155 @@
+use super::{
+    decode_image, get_page_images, open, Dedupe, ExtractOptions, ExtractReport, Method, Seen,
+};
+use crate::{RawImage, Result, VortexError};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+/// Walk the pages and decode their images on the current rayon thread pool, handing every
+/// image to `f` on one of its threads.
+///
+/// `f` also gets the position of the image in the page walk, failed, skipped and duplicate
+/// images keep their position so it stays the same from run to run whatever the number of
+/// threads.
+/// Errors of `f` are wrapped in [`VortexError::Write`] with the page and object of the image,
+/// they stop the extraction or are collected in the report in lenient mode.
+pub fn par_for_each_image<F>(
+    method: Method,
+    options: &ExtractOptions,
+    f: F,
+) -> Result<ExtractReport>
+where
+    F: Fn(usize, RawImage) -> Result<()> + Sync,
+{
+    let file = open(method)?;
+    let pages = options.page_indices(file.num_pages())?;
+
+    let mut report = ExtractReport::default();
+    let mut seen = Seen::new(options.dedupe);
+
+    // Position in the page walk of the next image
+    let mut position = 0;
+
+    // Pages are walked a few at a time so only their undecoded images, inline images with
+    // all their data, are held at once like `ImageIter` does
+    let threads = rayon::current_num_threads();
+
+    for pages in pages.chunks(threads) {
+        let walked = pages
+            .par_iter()
+            .map(|&index| -> Result<_> {
+                let page = file.get_page(index)?;
+                get_page_images(&page, &file)
+            })
+            .collect::<Vec<_>>();
+
+        let mut pending = vec![];
+
+        for (&index, images) in pages.iter().zip(walked) {
+            let images = match images {
+                Ok(images) => images,
+                Err(e) => {
+                    let e = VortexError::Page {
+                        page: index + 1,
+                        source: Box::new(e),
+                    };
+                    report.record(e, options.lenient)?;
+                    continue;
+                }
+            };
+
+            log::debug!("page {index} : total images {}", images.len());
+
+            for (page_index, image) in images.into_iter().enumerate() {
+                pending.push((position, (index + 1, page_index, image)));
+                position += 1;
+            }
+        }
+
+        let dedupe = options.dedupe != Dedupe::Off;
+
+        // Images are decoded a chunk at a time and copies are then dropped in page order,
+        // whichever thread finishes first, while memory stays bounded
+        for chunk in pending.chunks(threads * 4) {
+            // Only the first use of an object not met before is decoded, the others are
+            // copies. Dropping them has to wait for the images before them to be found
+            // copies by content or not, just like `ImageIter` does.
+            let mut ids = HashSet::new();
+            let first_uses = chunk
+                .iter()
+                .map(|(_, (_, _, image))| match image.object_id() {
+                    Some(id) if dedupe => !seen.knows(id) && ids.insert(id),
+                    _ => true,
+                })
+                .collect::<Vec<_>>();
+
+            let decoded = chunk
+                .par_iter()
+                .zip(first_uses)
+                .map(|((_, (page, page_index, image)), first_use)| -> Result<_> {
+                    if !first_use {
+                        return Ok(None);
+                    }
+
+                    let img = decode_image(image, Some(*page), *page_index, &file, options)?;
+                    Ok(img.map(|img| (options.dedupe.hash(&img), img)))
+                })
+                .collect::<Vec<_>>();
+
+            let mut kept = vec![];
+
+            for ((i, (page, page_index, image)), result) in chunk.iter().zip(decoded) {
+                let duplicates = &mut report.duplicates;
+
+                if seen.is_reference_copy(image.object_id(), *page, *page_index, duplicates) {
+                    continue;
+                }
+
+                match result {
+                    Ok(Some((hash, img))) => {
+                        if !seen.is_content_copy(&img, hash, &mut report.duplicates) {
+                            kept.push((*i, img));
+                        }
+                    }
+                    Ok(None) => {}
+                    Err(e) => report.record(e, options.lenient)?,
+                }
+            }
+
+            let written = kept
+                .into_par_iter()
+                .map(|(i, img)| {
+                    let (page, object_id) = (img.page, img.object_id);
+                    f(i, img).map_err(|e| VortexError::write(page, object_id, e))
+                })
+                .collect::<Vec<_>>();
+
+            for result in written {
+                match result {
+                    Ok(()) => report.extracted += 1,
+                    Err(e) => report.record(e, options.lenient)?,
+                }
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// Decode every image on the current rayon thread pool, in the order [`ImageIter`] yields them
+///
+/// [`ImageIter`]: super::ImageIter
+pub fn par_extract_images(method: Method, options: &ExtractOptions) -> Result<Vec<RawImage>> {
+    let images = Mutex::new(vec![]);
+
+    par_for_each_image(method, options, |i, img| {
+        images.lock().unwrap().push((i, img));
+        Ok(())
+    })?;
+
+    let mut images = images.into_inner().unwrap();
+    images.sort_by_key(|&(i, _)| i);
+
+    Ok(images.into_iter().map(|(_, img)| img).collect())
+}
@@ -0,0 +1,89 @@
+use crate::{Result, VortexError};
+use std::str::FromStr;
+
+/// Selection of 1-based pages written like `1-5,9,20-`.
+///
+/// An open ended span such as `20-` runs to the last page of the document.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PageRange {
+    spans: Vec<(u32, Option<u32>)>,
+}
+
+impl PageRange {
+    /// Whether the 1-based `page` is part of the selection
+    pub fn contains(&self, page: u32) -> bool {
+        self.spans
+            .iter()
+            .any(|&(start, end)| page >= start && !matches!(end, Some(end) if page > end))
+    }
+
+    /// Sorted 0-based indices of the selected pages in a document with `num_pages` pages.
+    /// Pages past the last one are left out, it is an error when no page is left.
+    pub fn indices(&self, num_pages: u32) -> Result<Vec<u32>> {
+        for &(start, end) in &self.spans {
+            if start > num_pages {
+                let span = match end {
+                    Some(end) if end == start => start.to_string(),
+                    Some(end) => format!("{start}-{end}"),
+                    None => format!("{start}-"),
+                };
+                log::warn!("skipping pages {span} : the document has {num_pages} pages");
+            }
+        }
+
+        let indices = (1..=num_pages)
+            .filter(|&page| self.contains(page))
+            .map(|page| page - 1)
+            .collect::<Vec<_>>();
+
+        if indices.is_empty() {
+            return Err(VortexError::InvalidPageRange(format!(
+                "No selected page in a document of {num_pages} pages"
+            )));
+        }
+
+        Ok(indices)
+    }
+}
+
+fn parse_page(s: &str) -> Result<u32> {
+    match s.trim().parse::<u32>() {
+        Ok(0) => Err(VortexError::InvalidPageRange(
+            "Pages are numbered from 1".to_owned(),
+        )),
+        Ok(page) => Ok(page),
+        Err(_) => Err(VortexError::InvalidPageRange(format!(
+            "Invalid page number {}",
+            s.trim()
+        ))),
+    }
+}
+
+impl FromStr for PageRange {
+    type Err = VortexError;
+    fn from_str(s: &str) -> Result<Self> {
+        let mut spans = vec![];
+
+        for part in s.split(',') {
+            let span = match part.split_once('-') {
+                Some((start, end)) if end.trim().is_empty() => (parse_page(start)?, None),
+                Some((start, end)) => (parse_page(start)?, Some(parse_page(end)?)),
+                None => {
+                    let page = parse_page(part)?;
+                    (page, Some(page))
+                }
+            };
+
+            if matches!(span, (start, Some(end)) if start > end) {
+                return Err(VortexError::InvalidPageRange(format!(
+                    "Page range {} starts after its end",
+                    part.trim()
+                )));
+            }
+
+            spans.push(span);
+        }
+
+        Ok(PageRange { spans })
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{Result, VortexError};
+
+/// zlib effort used for PNG output
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PngCompression {
+    Fast,
+    #[default]
+    Default,
+    Best,
+}
+
+/// Compression applied to the strips of a TIFF file
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TiffCompression {
+    None,
+    #[default]
+    Lzw,
+    Deflate,
+}
+
+/// WebP encoding, lossy with a quality from 0 to 100 or lossless
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebPMode {
+    Lossy(u8),
+    Lossless,
+}
+
+impl Default for WebPMode {
+    fn default() -> Self {
+        WebPMode::Lossy(80)
+    }
+}
+
+/// AVIF quality from 0 to 100 and encoder speed from 1 (slowest) to 10 (fastest)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AvifOptions {
+    pub quality: u8,
+    pub speed: u8,
+}
+
+impl Default for AvifOptions {
+    fn default() -> Self {
+        AvifOptions {
+            quality: 80,
+            speed: 4,
+        }
+    }
+}
+
+/// Encoder options written after the format name like `jpeg:85` or `avif:quality=70,speed=6`.
+///
+/// A bare value is a shorthand for the main option of the format, e.g. the quality of a JPEG.
+pub(crate) struct OptionList<'a> {
+    options: Vec<(Option<&'a str>, &'a str)>,
+}
+
+impl<'a> OptionList<'a> {
+    pub(crate) fn parse(s: Option<&'a str>) -> Self {
+        let options = s
+            .into_iter()
+            .flat_map(|s| s.split(','))
+            .filter(|option| !option.is_empty())
+            .map(|option| match option.split_once('=') {
+                Some((key, value)) => (Some(key.trim()), value.trim()),
+                None => (None, option.trim()),
+            })
+            .collect();
+
+        OptionList { options }
+    }
+
+    /// Visit every option, `key` is `None` for bare values
+    pub(crate) fn apply(&self, mut f: impl FnMut(Option<&str>, &str) -> Result<()>) -> Result<()> {
+        self.options
+            .iter()
+            .try_for_each(|&(key, value)| f(key, value))
+    }
+}
+
+fn invalid(msg: &str) -> VortexError {
+    VortexError::InvalidFormat(msg.to_owned())
+}
+
+pub(crate) fn parse_percent(value: &str) -> Result<u8> {
+    match value.parse::<u8>() {
+        Ok(value) if value <= 100 => Ok(value),
+        _ => Err(VortexError::InvalidFormat(format!(
+            "Quality must be a number from 0 to 100, got {value}"
+        ))),
+    }
+}
+
+pub(crate) fn parse_png(options: &OptionList) -> Result<PngCompression> {
+    let mut compression = PngCompression::default();
+
+    options.apply(|key, value| {
+        compression = match (key, value) {
+            (None | Some("compression"), "fast") => PngCompression::Fast,
+            (None | Some("compression"), "default") => PngCompression::Default,
+            (None | Some("compression"), "best") => PngCompression::Best,
+            _ => {
+                return Err(invalid(
+                    "Invalid png option, expected compression=fast|default|best",
+                ))
+            }
+        };
+        Ok(())
+    })?;
+
+    Ok(compression)
+}
+
+pub(crate) fn parse_tiff(options: &OptionList) -> Result<TiffCompression> {
+    let mut compression = TiffCompression::default();
+
+    options.apply(|key, value| {
+        compression = match (key, value) {
+            (None | Some("compression"), "none") => TiffCompression::None,
+            (None | Some("compression"), "lzw") => TiffCompression::Lzw,
+            (None | Some("compression"), "deflate") => TiffCompression::Deflate,
+            _ => {
+                return Err(invalid(
+                    "Invalid tiff option, expected compression=none|lzw|deflate",
+                ))
+            }
+        };
+        Ok(())
+    })?;
+
+    Ok(compression)
+}
+
+pub(crate) fn parse_webp(options: &OptionList) -> Result<WebPMode> {
+    let mut mode = WebPMode::default();
+
+    options.apply(|key, value| {
+        mode = match (key, value) {
+            (None, "lossless") => WebPMode::Lossless,
+            (None, "lossy") => WebPMode::default(),
+            (None | Some("quality"), quality) => WebPMode::Lossy(parse_percent(quality)?),
+            _ => {
+                return Err(invalid(
+                    "Invalid webp option, expected lossless or quality=0-100",
+                ))
+            }
+        };
+        Ok(())
+    })?;
+
+    Ok(mode)
+}
+
+pub(crate) fn parse_avif(options: &OptionList) -> Result<AvifOptions> {
+    let mut avif = AvifOptions::default();
+
+    options.apply(|key, value| {
+        match key {
+            None | Some("quality") => avif.quality = parse_percent(value)?,
+            Some("speed") => {
+                avif.speed = match value.parse() {
+                    Ok(speed @ 1..=10) => speed,
+                    _ => return Err(invalid("Avif speed must be a number from 1 to 10")),
+                }
+            }
+            _ => {
+                return Err(invalid(
+                    "Invalid avif option, expected quality=0-100 or speed=1-10",
+                ))
+            }
+        }
+        Ok(())
+    })?;
+
+    Ok(avif)
+}
+
+pub(crate) fn parse_jpeg(options: &OptionList, default: u8) -> Result<u8> {
+    let mut quality = default;
+
+    options.apply(|key, value| {
+        match key {
+            None | Some("quality") => quality = parse_percent(value)?,
+            _ => return Err(invalid("Invalid jpeg option, expected quality=0-100")),
+        }
+        Ok(())
+    })?;
+
+    // The JPEG encoder treats 0 as an invalid quality
+    Ok(quality.max(1))
+}
+
+/// Formats without options still reject anything written after the name
+pub(crate) fn parse_none(options: &OptionList, name: &str) -> Result<()> {
+    options.apply(|_, _| {
+        Err(VortexError::InvalidFormat(format!(
+            "{name} does not take encoder options"
+        )))
+    })
+}
@@ -1,14 +1,24 @@
 use super::Extract;
-use crate::{err, ImgError};
+use crate::format::{self, AvifOptions, OptionList, PngCompression, TiffCompression, WebPMode};
+use crate::VortexError;
 use image::ImageOutputFormat;
+use pdf::enc::StreamFilter;
 use pdf::object::ImageDict;
-use std::{ops::Deref, str::FromStr};
+use std::{ops::Deref, str::FromStr, sync::Arc};
 
 #[derive(Clone, Copy)]
 pub enum ImageFormat {
     Jpeg(u8),
-    Png,
+    Png(PngCompression),
     Jp2k,
+    WebP(WebPMode),
+    Tiff(TiffCompression),
+    Bmp,
+    Gif,
+    Avif(AvifOptions),
+    Qoi,
+    /// Picked per image, see [`ImageFormat::for_image`]
+    Auto,
 }
 
 impl Default for ImageFormat {
@@ -19,15 +29,98 @@ impl Default for ImageFormat {
 
 const DEFAULT_JPEG_QUALITY: u8 = 100;
 
+impl ImageFormat {
+    /// File extension for images written in this format
+    pub fn extension(&self) -> &'static str {
+        use ImageFormat::*;
+        match self {
+            Jpeg(_) => "jpeg",
+            Png(_) => "png",
+            Jp2k => "jp2",
+            WebP(_) => "webp",
+            Tiff(_) => "tiff",
+            Bmp => "bmp",
+            Gif => "gif",
+            Avif(_) => "avif",
+            Qoi => "qoi",
+            Auto => "png",
+        }
+    }
+
+    /// Whether the format can store 16 bits per channel
+    pub fn supports_16_bit(&self) -> bool {
+        use ImageFormat::*;
+        matches!(self, Png(_) | Jp2k | Tiff(_) | Auto)
+    }
+
+    /// Whether the format can store an alpha channel
+    pub fn supports_alpha(&self) -> bool {
+        use ImageFormat::*;
+        matches!(
+            self,
+            Png(_) | Jp2k | WebP(_) | Tiff(_) | Bmp | Gif | Avif(_) | Qoi | Auto
+        )
+    }
+
+    /// Whether the format can store single channel gray images, the others need RGB
+    pub fn supports_gray(&self) -> bool {
+        use ImageFormat::*;
+        matches!(self, Jpeg(_) | Png(_) | Jp2k | Tiff(_) | Bmp | Auto)
+    }
+
+    /// Concrete format to write `image` in. `Auto` keeps JPEG for DCT encoded photos,
+    /// TIFF for CCITT fax scans and PNG for everything else, which covers images with
+    /// alpha, line art and 1-bit data. Other formats are returned as they are.
+    pub fn for_image(self, image: &RawImage) -> ImageFormat {
+        use ImageFormat::*;
+
+        if !matches!(self, Auto) {
+            return self;
+        }
+
+        match image.filters.last() {
+            Some(StreamFilter::DCTDecode(_)) if image.mask.is_none() => Jpeg(DEFAULT_JPEG_QUALITY),
+            Some(StreamFilter::CCITTFaxDecode(_)) => Tiff(TiffCompression::default()),
+            _ => Png(PngCompression::default()),
+        }
+    }
+}
+
 impl FromStr for ImageFormat {
-    type Err = Box<dyn std::error::Error>;
+    type Err = VortexError;
+    /// Parse a format name optionally followed by encoder options, e.g. `jpeg:85`,
+    /// `png:compression=best`, `webp:lossless` or `tiff:deflate`
     fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
         use ImageFormat::*;
-        Ok(match s {
-            "jpeg" => Jpeg(DEFAULT_JPEG_QUALITY),
-            "png" => Png,
-            "jp2k" => Jp2k,
-            _ => return err!("Invalid format"),
+
+        let (name, options) = match s.split_once(':') {
+            Some((name, options)) => (name, Some(options)),
+            None => (s, None),
+        };
+        let options = OptionList::parse(options);
+
+        Ok(match name {
+            "jpeg" | "jpg" => Jpeg(format::parse_jpeg(&options, DEFAULT_JPEG_QUALITY)?),
+            "png" => Png(format::parse_png(&options)?),
+            "jp2k" if cfg!(feature = "jpeg2000") => {
+                format::parse_none(&options, name)?;
+                Jp2k
+            }
+            "jp2k" => return Err(VortexError::missing_feature(name, "jpeg2000")),
+            "webp" => WebP(format::parse_webp(&options)?),
+            "tiff" => Tiff(format::parse_tiff(&options)?),
+            "avif" if cfg!(feature = "avif") => Avif(format::parse_avif(&options)?),
+            "avif" => return Err(VortexError::missing_feature(name, "avif")),
+            "bmp" | "gif" | "qoi" | "auto" => {
+                format::parse_none(&options, name)?;
+                match name {
+                    "bmp" => Bmp,
+                    "gif" => Gif,
+                    "qoi" => Qoi,
+                    _ => Auto,
+                }
+            }
+            _ => return Err(VortexError::InvalidFormat(format!("Invalid format {name}"))),
         })
     }
 }
@@ -36,8 +129,15 @@ impl From<ImageOutputFormat> for ImageFormat {
     fn from(value: ImageOutputFormat) -> Self {
         use ImageOutputFormat::*;
         match value {
-            Png => ImageFormat::Png,
+            Png => ImageFormat::Png(PngCompression::default()),
             Jpeg(q) => ImageFormat::Jpeg(q),
+            WebP => ImageFormat::WebP(WebPMode::default()),
+            Tiff => ImageFormat::Tiff(TiffCompression::default()),
+            Bmp => ImageFormat::Bmp,
+            Gif => ImageFormat::Gif,
+            #[cfg(feature = "avif")]
+            Avif => ImageFormat::Avif(AvifOptions::default()),
+            Qoi => ImageFormat::Qoi,
             _ => ImageFormat::Jpeg(DEFAULT_JPEG_QUALITY),
         }
     }
@@ -48,9 +148,16 @@ impl std::fmt::Display for ImageFormat {
         use ImageFormat::*;
 
         match self {
-            Png => f.write_str("png"),
+            Png(_) => f.write_str("png"),
             Jpeg(_) => f.write_str("jpeg"),
             Jp2k => f.write_str("jp2k"),
+            WebP(_) => f.write_str("webp"),
+            Tiff(_) => f.write_str("tiff"),
+            Bmp => f.write_str("bmp"),
+            Gif => f.write_str("gif"),
+            Avif(_) => f.write_str("avif"),
+            Qoi => f.write_str("qoi"),
+            Auto => f.write_str("auto"),
         }
     }
 }
@@ -60,22 +167,93 @@ impl From<ImageFormat> for ImageOutputFormat {
         use ImageOutputFormat::*;
         match value {
             ImageFormat::Jpeg(q) => Jpeg(q),
-            ImageFormat::Png => Png,
-            _ => Jpeg(DEFAULT_JPEG_QUALITY),
+            // Encoder options are applied by the writer which drives these encoders itself
+            ImageFormat::Png(_) => Png,
+            ImageFormat::WebP(_) => WebP,
+            ImageFormat::Tiff(_) => Tiff,
+            #[cfg(feature = "avif")]
+            ImageFormat::Avif(_) => Avif,
+            ImageFormat::Bmp => Bmp,
+            ImageFormat::Gif => Gif,
+            ImageFormat::Qoi => Qoi,
+            ImageFormat::Auto => Png,
+            // Encoded by the writer itself, `image` has no JPEG 2000 encoder
+            ImageFormat::Jp2k => Unsupported(value.to_string()),
+            #[cfg(not(feature = "avif"))]
+            ImageFormat::Avif(_) => Unsupported(value.to_string()),
+        }
+    }
+}
+
+/// Transparency attached to an image through its `SMask` or `Mask` entry
+#[derive(Clone)]
+pub enum Mask {
+    /// Soft mask, the gray levels of the mask are the alpha channel
+    Soft(Box<RawImage>),
+    /// Stencil mask, samples painted by the mask are opaque, the rest transparent
+    Stencil(Box<RawImage>),
+    /// `[min max ...]` ranges per color component, matching samples are transparent
+    ColorKey(Vec<u32>),
+}
+
+/// Image stream still compressed with its image codec, e.g. a JPEG file
+#[derive(Clone)]
+pub struct Encoded {
+    pub filter: StreamFilter,
+    pub data: Arc<[u8]>,
+}
+
+impl Encoded {
+    /// Extension of the file the stream can be written to byte for byte, `None`
+    /// when the codec has no standalone file format
+    pub fn extension(&self) -> Option<&'static str> {
+        const JP2_SIGNATURE: &[u8] = b"\x00\x00\x00\x0cjP  ";
+
+        match self.filter {
+            StreamFilter::DCTDecode(_) => Some("jpg"),
+            StreamFilter::JPXDecode if self.data.starts_with(JP2_SIGNATURE) => Some("jp2"),
+            StreamFilter::JPXDecode => Some("j2k"),
+            _ => None,
         }
     }
 }
 
 pub struct RawImage {
     data: Vec<u8>,
+    decoded: bool,
     pub image_dict: ImageDict,
+    pub mask: Option<Mask>,
+    /// Filters the stream was compressed with, the image codec comes last
+    pub filters: Vec<StreamFilter>,
+    /// Original stream of images written without re-encoding, see
+    /// [`ExtractOptions::raw`] and [`ExtractOptions::copy_jpeg`]
+    ///
+    /// [`ExtractOptions::raw`]: crate::extractor::ExtractOptions::raw
+    /// [`ExtractOptions::copy_jpeg`]: crate::extractor::ExtractOptions::copy_jpeg
+    pub encoded: Option<Encoded>,
+    /// 1-based page the image was found on
+    pub page: Option<u32>,
+    /// Position of the image among the images of its page, from 0
+    pub page_index: usize,
+    /// Resource name of the XObject, `None` for inline images
+    pub name: Option<String>,
+    /// Object number of the XObject, `None` for inline images
+    pub object_id: Option<u64>,
 }
 
 impl Clone for RawImage {
     fn clone(&self) -> Self {
         Self {
             data: self.data.clone(),
+            decoded: self.decoded,
             image_dict: self.image_dict.clone(),
+            mask: self.mask.clone(),
+            filters: self.filters.clone(),
+            encoded: self.encoded.clone(),
+            page: self.page,
+            page_index: self.page_index,
+            name: self.name.clone(),
+            object_id: self.object_id,
         }
     }
 }
@@ -86,7 +264,93 @@ impl RawImage {
     pub fn new(source: &[u8], image_dict: ImageDict) -> Self {
         Self {
             data: source.to_vec(),
+            decoded: true,
+            image_dict,
+            mask: None,
+            filters: vec![],
+            encoded: None,
+            page: None,
+            page_index: 0,
+            name: None,
+            object_id: None,
+        }
+    }
+
+    pub fn with_mask(mut self, mask: Option<Mask>) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    pub fn with_filters(mut self, filters: Vec<StreamFilter>) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    pub fn with_encoded(mut self, encoded: Option<Encoded>) -> Self {
+        self.encoded = encoded;
+        self
+    }
+
+    /// Where in the document the image was found
+    pub fn with_origin(
+        mut self,
+        page: Option<u32>,
+        page_index: usize,
+        name: Option<String>,
+        object_id: Option<u64>,
+    ) -> Self {
+        self.page = page;
+        self.page_index = page_index;
+        self.name = name;
+        self.object_id = object_id;
+        self
+    }
+
+    /// Image that is only kept in its encoded form, its pixels are not decoded
+    pub fn from_encoded(encoded: Encoded, image_dict: ImageDict) -> Self {
+        Self {
+            data: vec![],
+            decoded: false,
             image_dict,
+            mask: None,
+            filters: vec![encoded.filter.clone()],
+            encoded: Some(encoded),
+            page: None,
+            page_index: 0,
+            name: None,
+            object_id: None,
+        }
+    }
+
+    /// Extension of the original stream when it is written without re-encoding
+    pub fn passthrough_extension(&self) -> Option<&'static str> {
+        self.encoded
+            .as_ref()
+            .filter(|_| !self.decoded)
+            .and_then(Encoded::extension)
+    }
+
+    /// Original stream to write as is when extracting to `format`. Undecoded images are
+    /// always written as is, `Auto` also copies JPEG streams whose colors need no fixing up.
+    pub fn passthrough(&self, format: ImageFormat) -> Option<&Encoded> {
+        let encoded = self.encoded.as_ref().filter(|e| e.extension().is_some())?;
+
+        if !self.decoded {
+            return Some(encoded);
+        }
+
+        let plain_jpeg = matches!(encoded.filter, StreamFilter::DCTDecode(_))
+            && self.mask.is_none()
+            && self.image_dict.decode.is_none();
+
+        (matches!(format, ImageFormat::Auto) && plain_jpeg).then_some(encoded)
+    }
+
+    /// Extension of the file written when extracting to `format`
+    pub fn output_extension(&self, format: ImageFormat) -> &'static str {
+        match self.passthrough(format).and_then(Encoded::extension) {
+            Some(extension) => extension,
+            None => format.for_image(self).extension(),
         }
     }
 }
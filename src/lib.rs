@@ -1,31 +1,15 @@
+mod error;
 pub mod extractor;
+mod format;
 mod img;
+pub mod manifest;
+pub mod template;
 pub mod writer;
 
-use std::{error::Error, fmt::Display};
-
-pub use img::{ImageFormat, RawImage};
+pub use error::VortexError;
+pub use format::{AvifOptions, PngCompression, TiffCompression, WebPMode};
+pub use img::{Encoded, ImageFormat, Mask, RawImage};
 
 pub trait Extract {}
 
-pub type Result<T> = std::result::Result<T, Box<dyn std::error::Error>>;
-
-#[macro_export]
-macro_rules! err {
-    ($msg:expr) => {
-        Err(Box::new(ImgError { msg: $msg }))
-    };
-}
-
-#[derive(Debug)]
-pub struct ImgError<'a> {
-    msg: &'a str,
-}
-
-impl<'a> Display for ImgError<'a> {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(self.msg)
-    }
-}
-
-impl<'a> Error for ImgError<'a> {}
+pub type Result<T> = std::result::Result<T, VortexError>;
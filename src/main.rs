@@ -1,80 +1,749 @@
-use clap::Parser;
+use clap::{ArgGroup, Parser, Subcommand};
 use log::LevelFilter;
 
 use std::{
-    fs::File,
-    io::BufWriter,
+    collections::HashSet,
+    io::{Cursor, Write},
     path::{Path, PathBuf},
+    process::ExitCode,
     str::FromStr,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+};
+use vortex::{
+    extractor::{
+        list_images, par_for_each_image, Dedupe, ExtractOptions, ExtractReport, ImageInfo, Method,
+        PageRange,
+    },
+    manifest::{Manifest, ManifestEntry},
+    template::{NameFields, NameTemplate, DEFAULT_NAME_TEMPLATE},
+    writer::{
+        create_output_writer,
+        io::{write_atomic, OverwritePolicy},
+    },
+    ImageFormat, Result,
 };
-use vortex::{extractor::extract_images, writer::create_output_writer, ImageFormat, Result};
 
 /// vortex is a tool to extract images from pdf files
 #[derive(Parser)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
+#[command(group(ArgGroup::new("existing").args(["overwrite", "skip_existing", "rename"])))]
 struct Args {
-    /// Pdf file to extract images from
-    pdf_file: PathBuf,
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// Pdf files, directories or glob patterns to extract images from, `-` reads a document
+    /// from stdin. Several documents each get a folder in the output folder
+    #[arg(required = true, value_name = "PDF FILES")]
+    pdf_files: Vec<PathBuf>,
+    /// Search directories for pdf files recursively
+    #[arg(short, long)]
+    recursive: bool,
     /// Folder to store extracted images
     #[arg(short, long, value_name = "OUTPUT FOLDER")]
     output_folder: Option<PathBuf>,
-    /// Logging level
+    /// Logging level i.e off, error, warn, info, debug, trace, overrides -v, -q and RUST_LOG
+    #[arg(long, global = true, value_name = "LEVEL")]
     log_level: Option<LevelFilter>,
-    /// Log file
+    /// Write logs to this file instead of stderr
+    #[arg(long, global = true, value_name = "LOG FILE")]
     log_file: Option<PathBuf>,
-    /// Optional  output image format i.e jpeg, png etc,
+    /// Log more, repeat for more detail
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Log less, repeat to turn logging off
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    quiet: u8,
+    /// Optional  output image format i.e jpeg, png etc, with encoder options i.e jpeg:85, webp:lossless, or auto to pick one per image
     #[arg(short, long)]
     target_format: Option<String>,
+    /// Pages to extract images from i.e 1-5,9,20-
+    #[arg(short, long)]
+    pages: Option<String>,
+    /// Write JPEG and JPEG 2000 images byte for byte as they are stored in the pdf
+    #[arg(long)]
+    raw: bool,
+    /// Skip pages and images that fail to decode instead of stopping
+    #[arg(long)]
+    lenient: bool,
+    /// Output file names, i.e {doc}/page{page}_{xobject}.{ext}, placeholders are {doc}, {page},
+    /// {index}, {page_index}, {xobject}, {objid}, {width}, {height}, {hash} and {ext}
+    #[arg(long, value_name = "TEMPLATE", default_value = DEFAULT_NAME_TEMPLATE)]
+    name_template: String,
+    /// Number of threads decoding and encoding images, every core by default
+    #[arg(short, long, value_name = "N")]
+    jobs: Option<usize>,
+    /// Write the metadata of every extracted image to a JSON, or CSV when the path ends in .csv
+    #[arg(long, value_name = "MANIFEST")]
+    manifest: Option<PathBuf>,
+    /// Extract images used on several pages once
+    #[arg(long)]
+    dedupe: bool,
+    /// Also extract images with identical pixels once, implies --dedupe
+    #[arg(long)]
+    dedupe_content: bool,
+    /// Replace output files that already exist
+    #[arg(long)]
+    overwrite: bool,
+    /// Keep output files that already exist and skip their images
+    #[arg(long)]
+    skip_existing: bool,
+    /// Write next to output files that already exist as name-1.ext, name-2.ext, ...
+    #[arg(long)]
+    rename: bool,
 }
 
-fn init_log(args: &Args) -> env_logger::Builder {
-    let mut builder = env_logger::builder();
+impl Args {
+    fn dedupe(&self) -> Dedupe {
+        if self.dedupe_content {
+            Dedupe::Content
+        } else if self.dedupe {
+            Dedupe::Reference
+        } else {
+            Dedupe::Off
+        }
+    }
+
+    /// Existing files are an error unless a policy was picked
+    fn overwrite_policy(&self) -> OverwritePolicy {
+        if self.overwrite {
+            OverwritePolicy::Overwrite
+        } else if self.skip_existing {
+            OverwritePolicy::SkipExisting
+        } else if self.rename {
+            OverwritePolicy::Rename
+        } else {
+            OverwritePolicy::Fail
+        }
+    }
+}
 
-    if !cfg!(debug_assertions) {
-        let log_file =
-            std::fs::File::create(args.log_file.clone().unwrap_or("log.txt".into())).unwrap();
-        builder.target(env_logger::Target::Pipe(Box::new(log_file)));
+#[derive(Subcommand)]
+enum Command {
+    /// Print the images of a pdf without decoding or writing them
+    #[command(visible_alias = "info")]
+    List(ListArgs),
+}
+
+#[derive(clap::Args)]
+struct ListArgs {
+    /// Pdf file to inspect, `-` reads the document from stdin
+    pdf_file: PathBuf,
+    /// Pages to list images from i.e 1-5,9,20-
+    #[arg(short, long)]
+    pages: Option<String>,
+    /// Skip pages and images that fail to load instead of stopping
+    #[arg(long)]
+    lenient: bool,
+    /// Print JSON instead of a table
+    #[arg(long)]
+    json: bool,
+}
+
+/// Level picked on the command line, `None` leaves it to `RUST_LOG`
+fn log_level(args: &Args) -> Option<LevelFilter> {
+    if args.log_level.is_some() {
+        return args.log_level;
     }
 
-    builder.filter_level(LevelFilter::Debug);
+    if args.verbose == 0 && args.quiet == 0 {
+        return None;
+    }
 
-    builder
+    // Warn is the default, every -v or -q moves one level from there
+    Some(match 2 + i32::from(args.verbose) - i32::from(args.quiet) {
+        i32::MIN..=0 => LevelFilter::Off,
+        1 => LevelFilter::Error,
+        2 => LevelFilter::Warn,
+        3 => LevelFilter::Info,
+        4 => LevelFilter::Debug,
+        _ => LevelFilter::Trace,
+    })
 }
 
-fn main() -> Result<()> {
+/// Log to stderr, or the log file, at the level from the flags, `RUST_LOG` or warn
+fn init_log(args: &Args) -> Result<env_logger::Builder> {
+    let mut builder =
+        env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("warn"));
+
+    if let Some(level) = log_level(args) {
+        builder.filter_level(level);
+    }
+
+    if let Some(ref path) = args.log_file {
+        let log_file = std::fs::File::create(path)?;
+        builder.target(env_logger::Target::Pipe(Box::new(log_file)));
+    }
+
+    Ok(builder)
+}
+
+fn main() -> Result<ExitCode> {
     let args = Args::parse();
 
-    init_log(&args).init();
+    init_log(&args)?.init();
+
+    if let Some(Command::List(list)) = args.command {
+        return list_command(list).map(|_| ExitCode::SUCCESS);
+    }
+
+    let out_dir: PathBuf = args
+        .output_folder
+        .unwrap_or_else(|| PathBuf::from("output"));
+
+    std::fs::create_dir_all(&out_dir)?;
+
+    if let Some(jobs) = args.jobs {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(jobs)
+            .build_global()
+            .map_err(std::io::Error::other)?;
+    }
+
+    let target_format = match args.target_format {
+        Some(ref format) => ImageFormat::from_str(format)?,
+        None => ImageFormat::default(),
+    };
+
+    let options = ExtractOptions {
+        pages: match args.pages {
+            Some(ref pages) => Some(PageRange::from_str(pages)?),
+            None => None,
+        },
+        raw: args.raw,
+        copy_jpeg: matches!(target_format, ImageFormat::Auto),
+        lenient: args.lenient,
+        dedupe: args.dedupe(),
+    };
+
+    let template = NameTemplate::from_str(&args.name_template)?;
+
+    let documents = find_documents(&args.pdf_files, args.recursive)?;
+
+    // Every document gets its own folder unless a single file was named
+    let batch = args.pdf_files.len() > 1
+        || args
+            .pdf_files
+            .iter()
+            .any(|path| path.is_dir() || is_glob(path));
+
+    let mut manifest = args.manifest.as_ref().map(|_| Manifest::default());
+
+    let mut summary = Summary::default();
+
+    for document in &documents {
+        let dir = if batch {
+            out_dir.join(&document.name)
+        } else {
+            out_dir.clone()
+        };
 
-    let out_dir: PathBuf = args.output_folder.unwrap_or(PathBuf::from_str("output")?);
+        let result = std::fs::create_dir_all(&dir)
+            .map_err(Into::into)
+            .and_then(|_| {
+                let output = Output {
+                    dir: &dir,
+                    format: target_format,
+                    template: &template,
+                    policy: args.overwrite_policy(),
+                };
+                extract_document(document, &output, &options, manifest.as_mut())
+            });
 
-    if !Path::new(&out_dir).exists() {
-        std::fs::create_dir(out_dir.clone())?;
+        match result {
+            Ok((report, existing)) => {
+                summary.images += report.extracted;
+                summary.existing += existing;
+                summary.skipped += report.failures.len();
+            }
+            Err(e) if batch => {
+                log::error!("{} : {}", document.path.display(), error_chain(&e));
+                summary.failed.push(document.path.clone());
+            }
+            Err(e) => return Err(e),
+        }
     }
 
-    let images = extract_images(vortex::extractor::Method::File(args.pdf_file))?;
+    if let (Some(manifest), Some(path)) = (manifest, args.manifest) {
+        manifest.save(&path)?;
+    }
+
+    if batch {
+        summary.print(documents.len());
+    }
+
+    Ok(if summary.failed.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Totals over every document of a batch
+#[derive(Default)]
+struct Summary {
+    images: usize,
+    /// Images whose file was already there
+    existing: usize,
+    skipped: usize,
+    failed: Vec<PathBuf>,
+}
+
+impl Summary {
+    fn print(&self, documents: usize) {
+        println!(
+            "{} documents, {} images extracted, {} already existed, {} skipped, {} documents failed",
+            documents,
+            self.images,
+            self.existing,
+            self.skipped,
+            self.failed.len()
+        );
+
+        for path in &self.failed {
+            println!("failed : {}", path.display());
+        }
+    }
+}
+
+/// Pdf file to extract and the name of its folder in batch mode
+struct Document {
+    path: PathBuf,
+    name: PathBuf,
+}
+
+fn is_glob(path: &Path) -> bool {
+    !path.exists() && path.to_string_lossy().contains(['*', '?', '['])
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("pdf"))
+}
+
+/// Expand the command line inputs into pdf files. Directories are searched for `.pdf` files,
+/// recursively with `--recursive`, and glob patterns are expanded, both in sorted order.
+/// A pattern matching no pdf is an error, most likely a typo.
+fn find_documents(inputs: &[PathBuf], recursive: bool) -> Result<Vec<Document>> {
+    let mut found = vec![];
+
+    for input in inputs {
+        if input.is_dir() {
+            let mut files = vec![];
+            find_pdfs(input, recursive, &mut files)?;
+
+            if files.is_empty() {
+                log::warn!("{} : no pdf files found", input.display());
+            }
+
+            found.extend(files.into_iter().map(|path| {
+                let name = path.strip_prefix(input).unwrap_or(&path).with_extension("");
+                (path, name)
+            }));
+        } else if is_glob(input) {
+            let pattern = input.to_string_lossy();
+            let paths = glob::glob(&pattern)
+                .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidInput, e))?;
+
+            let before = found.len();
+
+            for path in paths {
+                let path = path.map_err(glob::GlobError::into_error)?;
+
+                if is_pdf(&path) {
+                    let name = stem(&path);
+                    found.push((path, name));
+                }
+            }
+
+            if found.len() == before {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("{pattern} matches no pdf files"),
+                )
+                .into());
+            }
+        } else {
+            let name = if input.as_os_str() == "-" {
+                PathBuf::from("stdin")
+            } else {
+                stem(input)
+            };
+            found.push((input.clone(), name));
+        }
+    }
+
+    // Documents sharing a name are told apart by a counter
+    let mut names = HashSet::new();
+
+    Ok(found
+        .into_iter()
+        .map(|(path, base)| {
+            let mut name = base.clone();
+            let mut n = 1;
+
+            while !names.insert(name.clone()) {
+                n += 1;
+                name = PathBuf::from(format!("{}-{n}", base.display()));
+            }
+
+            Document { path, name }
+        })
+        .collect())
+}
+
+fn stem(path: &Path) -> PathBuf {
+    PathBuf::from(path.file_stem().unwrap_or(path.as_os_str()))
+}
+
+fn find_pdfs(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort();
+
+    for path in entries {
+        if path.is_dir() && recursive {
+            find_pdfs(&path, recursive, files)?;
+        } else if is_pdf(&path) {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// Where and how images are written
+struct Output<'a> {
+    dir: &'a Path,
+    format: ImageFormat,
+    template: &'a NameTemplate,
+    policy: OverwritePolicy,
+}
+
+/// Extract every image of `document` into the output folder, along with the number of
+/// images skipped because their file already exists
+fn extract_document(
+    document: &Document,
+    output: &Output,
+    options: &ExtractOptions,
+    manifest: Option<&mut Manifest>,
+) -> Result<(ExtractReport, usize)> {
+    let entries = Mutex::new(vec![]);
+    let existing = AtomicUsize::new(0);
+
+    let doc = document.name.to_string_lossy();
+
+    let skip = |path: &Path| {
+        log::info!("{} exists, skipping", path.display());
+        existing.fetch_add(1, Ordering::Relaxed);
+    };
+
+    let mut report = par_for_each_image(open_method(document.path.clone()), options, |i, img| {
+        let extension = img.output_extension(output.format);
+
+        let render = |data: &[u8]| -> Result<PathBuf> {
+            let name = output.template.render(&NameFields {
+                doc: &doc,
+                index: i,
+                image: &img,
+                extension,
+                data,
+            })?;
+            Ok(output.dir.join(name))
+        };
+
+        // Names without a hash are known before encoding, files already there are not redone
+        if output.policy == OverwritePolicy::SkipExisting && !output.template.uses_hash() {
+            let path = render(&[])?;
+            if path.exists() {
+                skip(&path);
+                return Ok(());
+            }
+        }
 
-    log::debug!("main : total images {}", images.len());
+        let mut data = Cursor::new(vec![]);
 
-    for (i, img) in images.iter().enumerate() {
-        let target_format = match args.target_format {
-            Some(ref format) => ImageFormat::from_str(format)?,
-            None => ImageFormat::default(),
+        let mut img_writer = create_output_writer(&img, output.format);
+
+        img_writer.write_to(&mut data)?;
+
+        let data = data.into_inner();
+
+        let path = render(&data)?;
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let path = match write_atomic(&path, &data, output.policy)? {
+            Some(path) => path,
+            None => {
+                skip(&path);
+                return Ok(());
+            }
         };
 
-        let writer = get_io_writer(&out_dir, &target_format, i);
+        if manifest.is_some() {
+            let entry = ManifestEntry::new(&img, &path, &data);
+            entries.lock().unwrap().push((i, img.page_index, entry));
+        }
 
-        let mut img_writer = create_output_writer(img, target_format);
+        Ok(())
+    })?;
 
-        img_writer.write_to(writer)?;
+    let existing = existing.into_inner();
+    report.extracted -= existing;
+
+    if let Some(manifest) = manifest {
+        let mut entries = entries.into_inner().unwrap();
+        entries.sort_by_key(|&(i, ..)| i);
+
+        for (_, page_index, mut entry) in entries {
+            if let Some(page) = entry.page {
+                entry.pages = report.pages_of(page, page_index);
+            }
+
+            manifest.push(entry);
+        }
+    }
+
+    log::debug!(
+        "{} : total images {}",
+        document.path.display(),
+        report.extracted
+    );
+
+    if !report.duplicates.is_empty() {
+        log::info!(
+            "{} : {} images used more than once were extracted once",
+            document.path.display(),
+            report.duplicates.len()
+        );
+    }
+
+    if existing > 0 {
+        log::info!(
+            "{} : {} images were already extracted",
+            document.path.display(),
+            existing
+        );
+    }
+
+    if !report.failures.is_empty() {
+        log::warn!(
+            "{} : {} pages or images were skipped",
+            document.path.display(),
+            report.failures.len()
+        );
+
+        for failure in &report.failures {
+            log::warn!("{}", error_chain(failure));
+        }
+    }
+
+    Ok((report, existing))
+}
+
+fn open_method(pdf_file: PathBuf) -> Method<'static> {
+    if pdf_file.as_os_str() == "-" {
+        Method::Reader(Box::new(std::io::stdin().lock()))
+    } else {
+        Method::File(pdf_file)
+    }
+}
+
+fn list_command(args: ListArgs) -> Result<()> {
+    let options = ExtractOptions {
+        pages: match args.pages {
+            Some(ref pages) => Some(PageRange::from_str(pages)?),
+            None => None,
+        },
+        lenient: args.lenient,
+        ..Default::default()
+    };
+
+    let images = list_images(open_method(args.pdf_file), &options)?;
+
+    let stdout = std::io::stdout().lock();
+
+    if args.json {
+        serde_json::to_writer_pretty(stdout, &images).map_err(std::io::Error::from)?;
+        println!();
+    } else {
+        print_table(stdout, &images)?;
     }
 
     Ok(())
 }
 
-fn get_io_writer(dir: &Path, target_format: &ImageFormat, index: usize) -> BufWriter<File> {
-    let filename = format!("extracted_image_{}.{}", index, target_format);
-    let filename = PathBuf::from_str(&filename).unwrap();
-    let joined_path = dir.join(filename);
-    let file = File::create(joined_path).unwrap();
-    BufWriter::new(file)
+fn print_table(mut w: impl Write, images: &[ImageInfo]) -> std::io::Result<()> {
+    fn or_dash<T: ToString>(value: &Option<T>) -> String {
+        value.as_ref().map_or_else(|| "-".to_owned(), T::to_string)
+    }
+
+    writeln!(
+        w,
+        "{:>5} {:<10} {:>7} {:>13} {:>4} {:<12} {:<24} {:>10}",
+        "page", "name", "object", "size", "bpc", "color space", "filters", "bytes"
+    )?;
+
+    for image in images {
+        writeln!(
+            w,
+            "{:>5} {:<10} {:>7} {:>13} {:>4} {:<12} {:<24} {:>10}",
+            image.page,
+            or_dash(&image.xobject),
+            or_dash(&image.object_id),
+            format!("{}x{}", image.width, image.height),
+            or_dash(&image.bits_per_component),
+            or_dash(&image.color_space),
+            image.filters.join(" "),
+            image.encoded_size,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// `error` followed by every error it was caused by
+fn error_chain(error: &dyn std::error::Error) -> String {
+    let mut chain = error.to_string();
+    let mut source = error.source();
+
+    while let Some(e) = source {
+        chain.push_str(&format!(" : {e}"));
+        source = e.source();
+    }
+
+    chain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vortex::VortexError;
+
+    fn touch(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, b"%PDF-1.4").unwrap();
+    }
+
+    fn names(documents: &[Document]) -> Vec<PathBuf> {
+        documents.iter().map(|d| d.name.clone()).collect()
+    }
+
+    #[test]
+    fn directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        touch(&root.join("b.PDF"));
+        touch(&root.join("a.pdf"));
+        touch(&root.join("notes.txt"));
+        touch(&root.join("sub/c.pdf"));
+
+        let documents = find_documents(&[root.to_owned()], false).unwrap();
+        assert_eq!(names(&documents), [PathBuf::from("a"), PathBuf::from("b")]);
+        assert_eq!(documents[0].path, root.join("a.pdf"));
+
+        let documents = find_documents(&[root.to_owned()], true).unwrap();
+        assert_eq!(
+            names(&documents),
+            [
+                PathBuf::from("a"),
+                PathBuf::from("b"),
+                PathBuf::from("sub/c")
+            ]
+        );
+
+        let empty = root.join("empty");
+        std::fs::create_dir(&empty).unwrap();
+        assert!(find_documents(&[empty], true).unwrap().is_empty());
+    }
+
+    #[test]
+    fn globs() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        touch(&root.join("a.pdf"));
+        touch(&root.join("b.PDF"));
+        touch(&root.join("notes.txt"));
+
+        assert!(is_glob(&root.join("*.pdf")));
+        assert!(is_glob(Path::new("missing/[ab].pdf")));
+        assert!(!is_glob(&root.join("a.pdf")));
+        assert!(!is_glob(Path::new("missing.pdf")));
+
+        let documents = find_documents(&[root.join("*")], false).unwrap();
+        assert_eq!(names(&documents), [PathBuf::from("a"), PathBuf::from("b")]);
+
+        let err = find_documents(&[root.join("*.pfd")], false).unwrap_err();
+        assert!(matches!(err, VortexError::Io(ref e) if e.kind() == std::io::ErrorKind::NotFound));
+    }
+
+    #[test]
+    fn skip_existing_counts_files_already_there() {
+        let dir = tempfile::tempdir().unwrap();
+        let document = Document {
+            path: PathBuf::from("resources/sample.pdf"),
+            name: PathBuf::from("sample"),
+        };
+        let options = ExtractOptions::default();
+
+        for (name, template) in [("index", DEFAULT_NAME_TEMPLATE), ("hash", "{hash}.{ext}")] {
+            let template = template.parse().unwrap();
+            let out_dir = dir.path().join(name);
+            let output = Output {
+                dir: &out_dir,
+                format: "png".parse().unwrap(),
+                template: &template,
+                policy: OverwritePolicy::SkipExisting,
+            };
+
+            let (first, existing) = extract_document(&document, &output, &options, None).unwrap();
+            assert!(first.extracted > 0);
+
+            // Identical images share a hash name, the later ones already find their file
+            let images = first.extracted + existing;
+
+            let (second, existing) = extract_document(&document, &output, &options, None).unwrap();
+            assert_eq!(second.extracted, 0);
+            assert_eq!(existing, images);
+        }
+    }
+
+    #[test]
+    fn name_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        touch(&root.join("a.pdf"));
+        touch(&root.join("other/a.pdf"));
+
+        let documents = find_documents(
+            &[
+                root.join("a.pdf"),
+                root.join("other/a.pdf"),
+                root.join("a.pdf"),
+                PathBuf::from("-"),
+            ],
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(
+            names(&documents),
+            [
+                PathBuf::from("a"),
+                PathBuf::from("a-2"),
+                PathBuf::from("a-3"),
+                PathBuf::from("stdin")
+            ]
+        );
+    }
 }
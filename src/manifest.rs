@@ -0,0 +1,188 @@
+use crate::{RawImage, Result};
+use pdf::enc::StreamFilter;
+use pdf::object::ColorSpace;
+use pdf::primitive::Primitive;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Metadata of one extracted image file
+#[derive(Clone, Debug, Serialize)]
+pub struct ManifestEntry {
+    /// 1-based page the image was found on
+    pub page: Option<u32>,
+    /// Every page the image appears on when its copies were deduplicated
+    pub pages: Vec<u32>,
+    /// Resource name of the XObject, `None` for inline images
+    pub xobject: Option<String>,
+    pub object_id: Option<u64>,
+    pub width: u32,
+    pub height: u32,
+    pub bits_per_component: Option<i32>,
+    pub color_space: Option<String>,
+    /// Filters of the original stream, the image codec comes last
+    pub filters: Vec<String>,
+    /// Size of the output file in bytes
+    pub size: u64,
+    pub path: PathBuf,
+    /// Hex encoded SHA-256 of the output file
+    pub sha256: String,
+}
+
+impl ManifestEntry {
+    /// Describe `image` written to `path` as `data`
+    pub fn new(image: &RawImage, path: &Path, data: &[u8]) -> Self {
+        let dict = &image.image_dict;
+
+        ManifestEntry {
+            page: image.page,
+            pages: image.page.into_iter().collect(),
+            xobject: image.name.clone(),
+            object_id: image.object_id,
+            width: dict.width,
+            height: dict.height,
+            bits_per_component: dict.bits_per_component,
+            color_space: dict.color_space.as_ref().map(color_space_name),
+            filters: image.filters.iter().map(filter_name).collect(),
+            size: data.len() as u64,
+            path: path.to_owned(),
+            sha256: hash(data),
+        }
+    }
+}
+
+/// Every image file written during an extraction
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct Manifest {
+    pub entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn push(&mut self, entry: ManifestEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn write_json<W: Write>(&self, w: W) -> Result<()> {
+        serde_json::to_writer_pretty(w, &self.entries).map_err(std::io::Error::from)?;
+        Ok(())
+    }
+
+    /// One row per file, the pages and filters are separated by spaces
+    pub fn write_csv<W: Write>(&self, w: W) -> Result<()> {
+        fn to_string<T: ToString>(value: &Option<T>) -> String {
+            value.as_ref().map(T::to_string).unwrap_or_default()
+        }
+
+        fn join(pages: &[u32]) -> String {
+            pages
+                .iter()
+                .map(u32::to_string)
+                .collect::<Vec<_>>()
+                .join(" ")
+        }
+
+        let mut w = csv::Writer::from_writer(w);
+
+        w.write_record([
+            "page",
+            "pages",
+            "xobject",
+            "object_id",
+            "width",
+            "height",
+            "bits_per_component",
+            "color_space",
+            "filters",
+            "size",
+            "path",
+            "sha256",
+        ])
+        .map_err(std::io::Error::from)?;
+
+        for entry in &self.entries {
+            w.write_record([
+                to_string(&entry.page),
+                join(&entry.pages),
+                to_string(&entry.xobject),
+                to_string(&entry.object_id),
+                entry.width.to_string(),
+                entry.height.to_string(),
+                to_string(&entry.bits_per_component),
+                to_string(&entry.color_space),
+                entry.filters.join(" "),
+                entry.size.to_string(),
+                entry.path.display().to_string(),
+                entry.sha256.clone(),
+            ])
+            .map_err(std::io::Error::from)?;
+        }
+
+        w.flush()?;
+        Ok(())
+    }
+
+    /// Write the manifest to `path`, as CSV when it ends in `.csv` and JSON otherwise
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let file = std::io::BufWriter::new(std::fs::File::create(path)?);
+
+        match path.extension() {
+            Some(extension) if extension.eq_ignore_ascii_case("csv") => self.write_csv(file),
+            _ => self.write_json(file),
+        }
+    }
+}
+
+/// Hex encoded SHA-256 of `data`
+pub(crate) fn hash(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Name of the filter as written in the pdf
+pub(crate) fn filter_name(filter: &StreamFilter) -> String {
+    use StreamFilter::*;
+
+    let name = match filter {
+        ASCIIHexDecode => "ASCIIHexDecode",
+        ASCII85Decode => "ASCII85Decode",
+        LZWDecode(_) => "LZWDecode",
+        FlateDecode(_) => "FlateDecode",
+        RunLengthDecode => "RunLengthDecode",
+        CCITTFaxDecode(_) => "CCITTFaxDecode",
+        JBIG2Decode => "JBIG2Decode",
+        DCTDecode(_) => "DCTDecode",
+        JPXDecode => "JPXDecode",
+        Crypt => "Crypt",
+    };
+
+    name.to_owned()
+}
+
+/// Family name of the color space as written in the pdf
+pub(crate) fn color_space_name(color_space: &ColorSpace) -> String {
+    use ColorSpace::*;
+
+    let name = match color_space {
+        DeviceGray => "DeviceGray",
+        DeviceRGB => "DeviceRGB",
+        DeviceCMYK => "DeviceCMYK",
+        CalGray(_) => "CalGray",
+        CalRGB(_) => "CalRGB",
+        CalCMYK(_) => "CalCMYK",
+        Icc(_) => "ICCBased",
+        Indexed(..) => "Indexed",
+        Separation(..) => "Separation",
+        DeviceN { .. } => "DeviceN",
+        Pattern => "Pattern",
+        Named(name) => name.as_str(),
+        Other(parts) => match parts.first() {
+            Some(Primitive::Name(name)) => name.as_str(),
+            _ => "Unknown",
+        },
+    };
+
+    name.to_owned()
+}
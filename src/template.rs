@@ -0,0 +1,183 @@
+use crate::{manifest, RawImage, Result, VortexError};
+use std::path::{Component, Path, PathBuf};
+use std::str::FromStr;
+
+/// Names used by the command line before templates existed
+pub const DEFAULT_NAME_TEMPLATE: &str = "extracted_image_{index}.{ext}";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Placeholder {
+    Doc,
+    Page,
+    Index,
+    PageIndex,
+    XObject,
+    ObjId,
+    Width,
+    Height,
+    Hash,
+    Ext,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Part {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// Output file name with placeholders filled in per image, e.g.
+/// `{doc}/page{page}/{xobject}_{width}x{height}.{ext}`. A `/` starts a subdirectory.
+///
+/// Placeholders are `{doc}`, `{page}`, `{index}`, `{page_index}`, `{xobject}`, `{objid}`,
+/// `{width}`, `{height}`, `{hash}` (SHA-256 of the file) and `{ext}`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NameTemplate {
+    parts: Vec<Part>,
+}
+
+/// Everything a template can refer to for one image
+pub struct NameFields<'a> {
+    /// Name of the document the image comes from
+    pub doc: &'a str,
+    /// Position of the image in the document, from 0
+    pub index: usize,
+    pub image: &'a RawImage,
+    pub extension: &'a str,
+    /// Contents of the output file, only read by `{hash}`
+    pub data: &'a [u8],
+}
+
+impl NameTemplate {
+    /// Relative path of the file described by `fields`, an error when it would not land
+    /// inside the output folder
+    pub fn render(&self, fields: &NameFields) -> Result<PathBuf> {
+        let mut name = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => name.push_str(text),
+                Part::Placeholder(placeholder) => {
+                    name.push_str(&sanitize(&Self::value(*placeholder, fields)))
+                }
+            }
+        }
+
+        let path = PathBuf::from(name);
+
+        if !is_inside(&path) {
+            return Err(VortexError::InvalidNameTemplate(format!(
+                "{} is not a relative path inside the output folder",
+                path.display()
+            )));
+        }
+
+        Ok(path)
+    }
+
+    /// Whether names depend on the contents of the file, which are then needed to render them
+    pub fn uses_hash(&self) -> bool {
+        self.parts.contains(&Part::Placeholder(Placeholder::Hash))
+    }
+
+    fn value(placeholder: Placeholder, fields: &NameFields) -> String {
+        use Placeholder::*;
+
+        let image = fields.image;
+
+        match placeholder {
+            Doc => fields.doc.to_owned(),
+            Page => image.page.unwrap_or(0).to_string(),
+            Index => fields.index.to_string(),
+            PageIndex => image.page_index.to_string(),
+            XObject => image.name.clone().unwrap_or_else(|| "inline".to_owned()),
+            ObjId => image
+                .object_id
+                .map_or_else(|| "inline".to_owned(), |id| id.to_string()),
+            Width => image.image_dict.width.to_string(),
+            Height => image.image_dict.height.to_string(),
+            Hash => manifest::hash(fields.data),
+            Ext => fields.extension.to_owned(),
+        }
+    }
+}
+
+/// Whether `path` is made of plain names only, without `.`, `..` or a root
+fn is_inside(path: &Path) -> bool {
+    path.components().next().is_some()
+        && path.components().all(|c| matches!(c, Component::Normal(_)))
+}
+
+/// Values are single path components, separators in them would create directories and
+/// `.` or `..` would leave the folder they are in
+fn sanitize(value: &str) -> String {
+    if !value.is_empty() && value.chars().all(|c| c == '.') {
+        return "_".to_owned();
+    }
+
+    value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '\0' => '_',
+            c => c,
+        })
+        .collect()
+}
+
+impl Default for NameTemplate {
+    fn default() -> Self {
+        DEFAULT_NAME_TEMPLATE.parse().unwrap()
+    }
+}
+
+impl FromStr for NameTemplate {
+    type Err = VortexError;
+    fn from_str(s: &str) -> Result<Self> {
+        use Placeholder::*;
+
+        let invalid = |msg: String| Err(VortexError::InvalidNameTemplate(msg));
+
+        let mut parts = vec![];
+        let mut rest = s;
+
+        while let Some(start) = rest.find('{') {
+            if start > 0 {
+                parts.push(Part::Text(rest[..start].to_owned()));
+            }
+
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => return invalid(format!("Unclosed placeholder in {s}")),
+            };
+
+            let placeholder = match &rest[start + 1..end] {
+                "doc" => Doc,
+                "page" => Page,
+                "index" => Index,
+                "page_index" => PageIndex,
+                "xobject" => XObject,
+                "objid" => ObjId,
+                "width" => Width,
+                "height" => Height,
+                "hash" => Hash,
+                "ext" => Ext,
+                name => return invalid(format!("Unknown placeholder {{{name}}}")),
+            };
+
+            parts.push(Part::Placeholder(placeholder));
+            rest = &rest[end + 1..];
+        }
+
+        if !rest.is_empty() {
+            parts.push(Part::Text(rest.to_owned()));
+        }
+
+        // Files always land inside the output folder, rendered names are checked again
+        if !is_inside(Path::new(s)) {
+            return invalid(format!(
+                "{s} must be a relative path inside the output folder"
+            ));
+        }
+
+        Ok(NameTemplate { parts })
+    }
+}
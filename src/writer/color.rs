@@ -0,0 +1,520 @@
+use super::samples;
+use crate::{RawImage, Result, VortexError};
+use image::{DynamicImage, GrayImage, ImageBuffer, RgbImage};
+use pdf::object::ColorSpace;
+use pdf::primitive::Primitive;
+use std::borrow::Cow;
+
+/// Error of image data holding fewer samples than its width and height call for
+pub(super) const SHORT_DATA: &str = "Image data is smaller than its dimensions";
+
+/// Number of components of a single sample in the color space
+fn components(color_space: &ColorSpace) -> Option<usize> {
+    use ColorSpace::*;
+    Some(match color_space {
+        DeviceGray | CalGray(_) | Indexed(..) | Separation(..) => 1,
+        DeviceRGB | CalRGB(_) => 3,
+        DeviceCMYK | CalCMYK(_) => 4,
+        DeviceN { names, .. } => names.len(),
+        Icc(icc) => icc.components as usize,
+        Other(parts) if lab_params(parts).is_some() => 3,
+        _ => return None,
+    })
+}
+
+/// Guess the color space from the amount of data when the image dictionary is
+/// missing one or the decoder produced a different layout, e.g. JPEG streams
+fn infer_color_space(len: usize, pixels: usize) -> ColorSpace {
+    match len / pixels.max(1) {
+        4 => ColorSpace::DeviceCMYK,
+        3 => ColorSpace::DeviceRGB,
+        _ => ColorSpace::DeviceGray,
+    }
+}
+
+/// Convert the decoded samples of `img` to an `image` buffer matching its color space
+/// and bit depth, after mapping them through the `Decode` array. 16 bit gray and RGB
+/// images keep their depth.
+pub(crate) fn to_dynamic_image(img: &RawImage) -> Result<DynamicImage> {
+    let (width, height) = (img.image_dict.width, img.image_dict.height);
+    let pixels = width as usize * height as usize;
+
+    if pixels == 0 {
+        return Err(VortexError::InvalidImage("Image has no pixels"));
+    }
+
+    let bpc = img.image_dict.bits_per_component.unwrap_or(8) as usize;
+
+    let device_gray = ColorSpace::DeviceGray;
+    let declared = match img.image_dict.color_space {
+        Some(ref cs) => components(cs).map(|n| (cs, n)),
+        None if img.image_dict.image_mask || bpc < 8 => Some((&device_gray, 1)),
+        None => None,
+    };
+
+    let packed_len = |n: usize| samples::packed_len(width as usize * n, height as usize, bpc);
+
+    let inferred;
+    let (color_space, n, bpc) = match declared {
+        Some((cs, n)) if img.len() == packed_len(n) => (cs, n, bpc),
+        // Decoders like DCT and CCITT hand back whole bytes whatever the dictionary says
+        Some((cs, n)) if bpc < 8 && img.len() >= pixels * n => (cs, n, 8),
+        Some((cs, n)) if img.len() >= packed_len(n) => (cs, n, bpc),
+        _ => {
+            inferred = infer_color_space(img.len(), pixels);
+            log::debug!("color space : inferred {:?} from data length", inferred);
+            (&inferred, components(&inferred).unwrap_or(1), 8)
+        }
+    };
+
+    let indexed = matches!(color_space, ColorSpace::Indexed(..));
+
+    // Lab samples are mapped through their own ranges instead
+    let decode = match img.image_dict.decode {
+        Some(ref decode) if !matches!(color_space, ColorSpace::Other(_)) => decode.get(..2 * n),
+        _ => None,
+    };
+
+    let mut data: Cow<[u8]> = match bpc {
+        8 => Cow::Borrowed(&img[..]),
+        16 => match convert16(color_space, img, decode, width, height) {
+            Some(img) => return img,
+            None => Cow::Owned(samples::to_u8(img)),
+        },
+        1 | 2 | 4 => Cow::Owned(samples::unpack(
+            img,
+            bpc,
+            width as usize * n,
+            height as usize,
+            !indexed,
+        )),
+        _ => return Err(VortexError::UnsupportedBitDepth(bpc as i32)),
+    };
+
+    if let Some(decode) = decode {
+        // Indexes are kept unscaled so they still address the color table
+        let max = if indexed { (1 << bpc.min(8)) - 1 } else { 255 };
+        apply_decode(data.to_mut(), decode, max as f32, indexed);
+    }
+
+    convert(color_space, &data, width, height)
+}
+
+/// Whether `decode` is the identity mapping `[0 max 0 max ...]`
+fn is_default_decode(decode: &[f32], max: f32) -> bool {
+    decode.chunks_exact(2).all(|d| d[0] == 0.0 && d[1] == max)
+}
+
+/// Map every sample in `0..=max` through the `[Dmin Dmax ...]` pair of its component
+fn apply_decode(data: &mut [u8], decode: &[f32], max: f32, indexed: bool) {
+    if is_default_decode(decode, if indexed { max } else { 1.0 }) {
+        return;
+    }
+
+    let tables = decode
+        .chunks_exact(2)
+        .map(|d| {
+            let mut table = [0u8; 256];
+            for (sample, out) in table.iter_mut().enumerate() {
+                let value = d[0] + sample as f32 / max * (d[1] - d[0]);
+                *out = if indexed {
+                    value.round().clamp(0.0, 255.0) as u8
+                } else {
+                    to_byte(value)
+                };
+            }
+            table
+        })
+        .collect::<Vec<_>>();
+
+    for pixel in data.chunks_exact_mut(tables.len()) {
+        for (sample, table) in pixel.iter_mut().zip(&tables) {
+            *sample = table[*sample as usize];
+        }
+    }
+}
+
+/// 16 bit conversion for color spaces that map directly to an `image` buffer
+fn convert16(
+    color_space: &ColorSpace,
+    data: &[u8],
+    decode: Option<&[f32]>,
+    width: u32,
+    height: u32,
+) -> Option<Result<DynamicImage>> {
+    use ColorSpace::*;
+
+    let channels = match color_space {
+        DeviceGray | CalGray(_) => 1,
+        DeviceRGB | CalRGB(_) => 3,
+        Icc(icc) if icc.components == 1 || icc.components == 3 => icc.components as usize,
+        _ => return None,
+    };
+
+    let len = width as usize * height as usize * channels;
+    let mut data = samples::to_u16(data)
+        .into_iter()
+        .take(len)
+        .collect::<Vec<_>>();
+
+    if let Some(decode) = decode.filter(|d| !is_default_decode(d, 1.0)) {
+        for pixel in data.chunks_exact_mut(channels) {
+            for (sample, d) in pixel.iter_mut().zip(decode.chunks_exact(2)) {
+                let value = d[0] + *sample as f32 / 65535.0 * (d[1] - d[0]);
+                *sample = (value.clamp(0.0, 1.0) * 65535.0).round() as u16;
+            }
+        }
+    }
+
+    let img = match channels {
+        1 => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageLuma16),
+        _ => ImageBuffer::from_raw(width, height, data).map(DynamicImage::ImageRgb16),
+    };
+
+    Some(match img {
+        Some(img) => Ok(img),
+        None => Err(VortexError::InvalidImage(SHORT_DATA)),
+    })
+}
+
+fn convert(color_space: &ColorSpace, data: &[u8], width: u32, height: u32) -> Result<DynamicImage> {
+    use ColorSpace::*;
+
+    let pixels = width as usize * height as usize;
+
+    match color_space {
+        DeviceGray | CalGray(_) => gray(data, width, height),
+        DeviceRGB | CalRGB(_) => rgb(data, width, height),
+        DeviceCMYK | CalCMYK(_) => rgb(&cmyk_to_rgb(data), width, height),
+        Icc(icc) => match icc.components {
+            1 => gray(data, width, height),
+            3 => rgb(data, width, height),
+            4 => rgb(&cmyk_to_rgb(data), width, height),
+            _ => match icc.alternate {
+                Some(ref alt) => convert(alt, data, width, height),
+                None => Err(VortexError::UnsupportedColorSpace(format!(
+                    "ICC profile with {} components",
+                    icc.components
+                ))),
+            },
+        },
+        Indexed(base, lookup) => {
+            let n = match components(base) {
+                Some(n) => n,
+                None => {
+                    return Err(VortexError::UnsupportedColorSpace(
+                        "Indexed with an unsupported base".to_owned(),
+                    ))
+                }
+            };
+
+            let mut expanded = Vec::with_capacity(pixels * n);
+
+            for &index in take_samples(data, pixels)? {
+                let start = index as usize * n;
+                match lookup.get(start..start + n) {
+                    Some(color) => expanded.extend_from_slice(color),
+                    None => expanded.extend(std::iter::repeat(0).take(n)),
+                }
+            }
+
+            convert(base, &expanded, width, height)
+        }
+        Separation(_, alt, tint) => {
+            // A single colorant, precompute the tint transform for every sample value
+            let n = components(alt).unwrap_or(1);
+            let mut out = vec![0.0; n];
+            let mut table = Vec::with_capacity(256 * n);
+
+            for sample in 0..=255u8 {
+                if tint.apply(&[sample as f32 / 255.0], &mut out).is_err() {
+                    // Without a usable tint transform show the amount of ink like a viewer does
+                    let ink = take_samples(data, pixels)?
+                        .iter()
+                        .map(|&s| 255 - s)
+                        .collect::<Vec<_>>();
+                    return gray(&ink, width, height);
+                }
+
+                table.extend(out.iter().map(|&v| to_byte(v)));
+            }
+
+            let expanded = take_samples(data, pixels)?
+                .iter()
+                .flat_map(|&s| table[s as usize * n..(s as usize + 1) * n].iter().copied())
+                .collect::<Vec<_>>();
+
+            convert(alt, &expanded, width, height)
+        }
+        DeviceN {
+            names, alt, tint, ..
+        } => {
+            let inputs = names.len();
+            let n = components(alt).unwrap_or(1);
+
+            if inputs == 0 {
+                return Err(VortexError::UnsupportedColorSpace(
+                    "DeviceN without colorants".to_owned(),
+                ));
+            }
+
+            let mut input = vec![0.0; inputs];
+            let mut out = vec![0.0; n];
+            let mut expanded = Vec::with_capacity(pixels * n);
+
+            for sample in take_samples(data, pixels * inputs)?.chunks_exact(inputs) {
+                input
+                    .iter_mut()
+                    .zip(sample)
+                    .for_each(|(i, &s)| *i = s as f32 / 255.0);
+
+                if tint.apply(&input, &mut out).is_err() {
+                    return Err(VortexError::UnsupportedColorSpace(
+                        "DeviceN tint transform failed".to_owned(),
+                    ));
+                }
+
+                expanded.extend(out.iter().map(|&v| to_byte(v)));
+            }
+
+            convert(alt, &expanded, width, height)
+        }
+        Other(parts) => match lab_params(parts) {
+            Some(params) => rgb(&lab_to_rgb(data, &params), width, height),
+            None => Err(VortexError::UnsupportedColorSpace(match parts.first() {
+                Some(Primitive::Name(name)) => name.as_str().to_owned(),
+                _ => "Unknown".to_owned(),
+            })),
+        },
+        _ => Err(VortexError::UnsupportedColorSpace(format!(
+            "{color_space:?}"
+        ))),
+    }
+}
+
+/// The first `len` samples of `data`
+fn take_samples(data: &[u8], len: usize) -> Result<&[u8]> {
+    data.get(..len).ok_or(VortexError::InvalidImage(SHORT_DATA))
+}
+
+fn gray(data: &[u8], width: u32, height: u32) -> Result<DynamicImage> {
+    let len = width as usize * height as usize;
+    match data
+        .get(..len)
+        .and_then(|d| GrayImage::from_raw(width, height, d.to_vec()))
+    {
+        Some(buf) => Ok(DynamicImage::ImageLuma8(buf)),
+        None => Err(VortexError::InvalidImage(SHORT_DATA)),
+    }
+}
+
+fn rgb(data: &[u8], width: u32, height: u32) -> Result<DynamicImage> {
+    let len = width as usize * height as usize * 3;
+    match data
+        .get(..len)
+        .and_then(|d| RgbImage::from_raw(width, height, d.to_vec()))
+    {
+        Some(buf) => Ok(DynamicImage::ImageRgb8(buf)),
+        None => Err(VortexError::InvalidImage(SHORT_DATA)),
+    }
+}
+
+fn to_byte(v: f32) -> u8 {
+    (v.clamp(0.0, 1.0) * 255.0).round() as u8
+}
+
+fn cmyk_to_rgb(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(4)
+        .flat_map(|p| {
+            let k = 255 - p[3] as u32;
+            [p[0], p[1], p[2]].map(|c| ((255 - c as u32) * k / 255) as u8)
+        })
+        .collect()
+}
+
+/// White point and a*, b* ranges of a `[/Lab << ... >>]` color space
+struct LabParams {
+    white: [f32; 3],
+    range: [f32; 4],
+}
+
+fn lab_params(parts: &[Primitive]) -> Option<LabParams> {
+    if parts.first()?.as_name().ok()? != "Lab" {
+        return None;
+    }
+
+    let dict = match parts.get(1)? {
+        Primitive::Dictionary(dict) => dict,
+        _ => return None,
+    };
+
+    let numbers = |key: &str| -> Option<Vec<f32>> {
+        dict.get(key)?
+            .as_array()
+            .ok()?
+            .iter()
+            .map(|p| p.as_number().ok())
+            .collect()
+    };
+
+    let white = numbers("WhitePoint")?;
+    let range = numbers("Range").unwrap_or_else(|| vec![-100.0, 100.0, -100.0, 100.0]);
+
+    Some(LabParams {
+        white: [*white.first()?, *white.get(1)?, *white.get(2)?],
+        range: [
+            *range.first()?,
+            *range.get(1)?,
+            *range.get(2)?,
+            *range.get(3)?,
+        ],
+    })
+}
+
+fn lab_to_rgb(data: &[u8], params: &LabParams) -> Vec<u8> {
+    fn finv(t: f32) -> f32 {
+        const DELTA: f32 = 6.0 / 29.0;
+        if t > DELTA {
+            t * t * t
+        } else {
+            3.0 * DELTA * DELTA * (t - 4.0 / 29.0)
+        }
+    }
+
+    fn gamma(c: f32) -> f32 {
+        if c <= 0.003_130_8 {
+            12.92 * c
+        } else {
+            1.055 * c.powf(1.0 / 2.4) - 0.055
+        }
+    }
+
+    let [xw, yw, zw] = params.white;
+    let [amin, amax, bmin, bmax] = params.range;
+
+    data.chunks_exact(3)
+        .flat_map(|p| {
+            let l = p[0] as f32 / 255.0 * 100.0;
+            let a = amin + p[1] as f32 / 255.0 * (amax - amin);
+            let b = bmin + p[2] as f32 / 255.0 * (bmax - bmin);
+
+            let fy = (l + 16.0) / 116.0;
+            let x = xw * finv(fy + a / 500.0);
+            let y = yw * finv(fy);
+            let z = zw * finv(fy - b / 200.0);
+
+            [
+                3.2406 * x - 1.5372 * y - 0.4986 * z,
+                -0.9689 * x + 1.8758 * y + 0.0415 * z,
+                0.0557 * x - 0.2040 * y + 1.0570 * z,
+            ]
+            .map(|c| to_byte(gamma(c.max(0.0))))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rgb_pixels(img: DynamicImage) -> Vec<u8> {
+        img.to_rgb8().into_raw()
+    }
+
+    #[test]
+    fn cmyk() {
+        let data = [0, 0, 0, 0, 255, 0, 0, 0, 0, 0, 0, 255, 0, 255, 255, 128];
+        assert_eq!(
+            cmyk_to_rgb(&data),
+            [255, 255, 255, 0, 255, 255, 0, 0, 0, 127, 0, 0]
+        );
+    }
+
+    #[test]
+    fn lab() {
+        let params = LabParams {
+            white: [0.9505, 1.0, 1.089],
+            range: [-128.0, 127.0, -128.0, 127.0],
+        };
+
+        // a* and b* of 128 are 0 in the -128..127 range, a neutral gray axis
+        let rgb = lab_to_rgb(&[255, 128, 128, 0, 128, 128], &params);
+
+        assert!(rgb[..3].iter().all(|&c| c >= 250), "{rgb:?}");
+        assert_eq!(&rgb[3..], &[0, 0, 0]);
+    }
+
+    #[test]
+    fn indexed() {
+        let lookup = vec![255, 0, 0, 0, 0, 255];
+        let color_space = ColorSpace::Indexed(Box::new(ColorSpace::DeviceRGB), lookup.into());
+
+        // Indexes past the end of the table are black
+        let img = convert(&color_space, &[1, 0, 7], 3, 1).unwrap();
+        assert_eq!(rgb_pixels(img), [0, 0, 255, 255, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn indexed_low_bit_depth() {
+        let lookup = vec![0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255];
+        let color_space = ColorSpace::Indexed(Box::new(ColorSpace::DeviceRGB), lookup.into());
+
+        // 2 bit indexes stay unscaled so they address the table
+        let indexes = samples::unpack(&[0b0001_1011], 2, 4, 1, false);
+        let img = convert(&color_space, &indexes, 4, 1).unwrap();
+
+        assert_eq!(rgb_pixels(img), [0, 0, 0, 255, 0, 0, 0, 255, 0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn sixteen_bit_keeps_depth() {
+        let data = [0x12, 0x34, 0xff, 0xff];
+        let img = convert16(&ColorSpace::DeviceGray, &data, None, 2, 1)
+            .unwrap()
+            .unwrap();
+
+        match img {
+            DynamicImage::ImageLuma16(buf) => assert_eq!(buf.into_raw(), [0x1234, 0xffff]),
+            img => panic!("expected 16 bit gray, got {:?}", img.color()),
+        }
+
+        assert!(convert16(&ColorSpace::DeviceCMYK, &data, None, 1, 1).is_none());
+    }
+
+    #[test]
+    fn decode_inverts() {
+        let mut data = vec![0, 128, 255];
+        apply_decode(&mut data, &[1.0, 0.0], 255.0, false);
+        assert_eq!(data, [255, 127, 0]);
+
+        // The identity mapping leaves the samples alone
+        let mut data = vec![0, 128, 255];
+        apply_decode(&mut data, &[0.0, 1.0], 255.0, false);
+        assert_eq!(data, [0, 128, 255]);
+
+        // Indexes of a 2 bit image map through [3 0]
+        let mut data = vec![0, 1, 2, 3];
+        apply_decode(&mut data, &[3.0, 0.0], 3.0, true);
+        assert_eq!(data, [3, 2, 1, 0]);
+
+        // Each component has its own pair
+        let mut data = vec![0, 0, 255, 255];
+        apply_decode(&mut data, &[1.0, 0.0, 0.0, 1.0], 255.0, false);
+        assert_eq!(data, [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn short_data_is_an_error() {
+        let lookup = vec![255, 0, 0];
+        let color_space = ColorSpace::Indexed(Box::new(ColorSpace::DeviceRGB), lookup.into());
+
+        assert!(matches!(
+            convert(&color_space, &[0], 2, 2),
+            Err(VortexError::InvalidImage(_))
+        ));
+        assert!(matches!(
+            convert(&ColorSpace::DeviceRGB, &[0; 5], 1, 2),
+            Err(VortexError::InvalidImage(_))
+        ));
+    }
+}
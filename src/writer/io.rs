@@ -1,9 +1,11 @@
+use crate::Result;
 use std::{
     fs::File,
-    io::{BufWriter, Seek, Write},
+    io::{BufWriter, ErrorKind, Seek, Write},
     net::TcpStream,
     path::{Path, PathBuf},
 };
+use tempfile::{NamedTempFile, PersistError};
 
 pub trait Writer: Write + Seek {}
 
@@ -28,17 +30,80 @@ pub enum WriteMethod {
     Network(TcpStream),
 }
 
-pub fn create_writer(inner: WriteMethod) -> impl WriterFactory {
+pub fn create_writer(inner: WriteMethod) -> Result<impl WriterFactory> {
     match inner {
-        WriteMethod::File(path) => {
-            let inner = if Path::new(&path).exists() {
-                File::open(path).unwrap()
-            } else {
-                File::create(path).unwrap()
-            };
-
-            FileWriterFactory { inner }
-        }
-        WriteMethod::Network(_) => todo!("Implement network writer"),
+        WriteMethod::File(path) => Ok(FileWriterFactory {
+            inner: File::create(path)?,
+        }),
+        WriteMethod::Network(_) => Err(std::io::Error::new(
+            ErrorKind::Unsupported,
+            "network output is not implemented",
+        )
+        .into()),
     }
 }
+
+/// What to do when an output file already exists
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Fail with an `AlreadyExists` error
+    #[default]
+    Fail,
+    /// Replace the existing file
+    Overwrite,
+    /// Keep the existing file and drop the new one
+    SkipExisting,
+    /// Write the new file as `name-1.ext`, `name-2.ext` and so on
+    Rename,
+}
+
+/// Write `data` to a temporary file next to `path` and move it into place, so readers never
+/// see a half written file. Returns where the file ended up, `None` when it was skipped.
+pub fn write_atomic(path: &Path, data: &[u8], policy: OverwritePolicy) -> Result<Option<PathBuf>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    let mut file = NamedTempFile::new_in(dir)?;
+    file.write_all(data)?;
+    file.as_file().sync_all()?;
+
+    if policy == OverwritePolicy::Overwrite {
+        file.persist(path).map_err(|e| e.error)?;
+        return Ok(Some(path.to_owned()));
+    }
+
+    let mut target = path.to_owned();
+    let mut n = 0;
+
+    loop {
+        // Moving without replacing is atomic, two writers racing for a name cannot both win
+        file = match file.persist_noclobber(&target) {
+            Ok(_) => return Ok(Some(target)),
+            Err(PersistError { error, file }) if error.kind() == ErrorKind::AlreadyExists => {
+                match policy {
+                    OverwritePolicy::SkipExisting => return Ok(None),
+                    OverwritePolicy::Rename => file,
+                    _ => return Err(error.into()),
+                }
+            }
+            Err(e) => return Err(e.error.into()),
+        };
+
+        n += 1;
+        target = numbered(path, n);
+    }
+}
+
+/// `dir/name.ext` to `dir/name-n.ext`
+fn numbered(path: &Path, n: usize) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+
+    let name = match path.extension() {
+        Some(extension) => format!("{stem}-{n}.{}", extension.to_string_lossy()),
+        None => format!("{stem}-{n}"),
+    };
+
+    path.with_file_name(name)
+}
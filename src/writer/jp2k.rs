@@ -0,0 +1,320 @@
+use crate::Result;
+use image::DynamicImage;
+use std::io::{Seek, Write};
+
+/// Encode `img` as a lossless JP2 file through OpenJPEG
+#[cfg(feature = "jpeg2000")]
+pub(crate) fn write<W: Write + Seek>(img: &DynamicImage, w: &mut W) -> Result<()> {
+    let encoded = encoder::encode(img)?;
+    w.write_all(&encoded)?;
+    Ok(())
+}
+
+#[cfg(not(feature = "jpeg2000"))]
+pub(crate) fn write<W: Write + Seek>(_img: &DynamicImage, _w: &mut W) -> Result<()> {
+    Err(crate::VortexError::missing_feature("jp2k", "jpeg2000"))
+}
+
+#[cfg(feature = "jpeg2000")]
+mod encoder {
+    use crate::{Result, VortexError};
+    use image::{ColorType, DynamicImage};
+    use openjpeg_sys as opj;
+    use std::ffi::c_void;
+    use std::io::{Cursor, Seek, SeekFrom, Write};
+
+    /// Components, precision and interleaved samples in the layout OpenJPEG expects
+    fn samples(img: &DynamicImage) -> (usize, u32, Vec<i32>) {
+        fn widen<T: Into<i32> + Copy>(raw: Vec<T>) -> Vec<i32> {
+            raw.into_iter().map(Into::into).collect()
+        }
+
+        match img.color() {
+            ColorType::L8 => (1, 8, widen(img.to_luma8().into_raw())),
+            ColorType::La8 => (2, 8, widen(img.to_luma_alpha8().into_raw())),
+            ColorType::L16 => (1, 16, widen(img.to_luma16().into_raw())),
+            ColorType::La16 => (2, 16, widen(img.to_luma_alpha16().into_raw())),
+            ColorType::Rgb16 => (3, 16, widen(img.to_rgb16().into_raw())),
+            ColorType::Rgba16 => (4, 16, widen(img.to_rgba16().into_raw())),
+            color if color.has_alpha() => (4, 8, widen(img.to_rgba8().into_raw())),
+            _ => (3, 8, widen(img.to_rgb8().into_raw())),
+        }
+    }
+
+    /// Owns the OpenJPEG handles so they are released on every exit path
+    struct Handles {
+        image: *mut opj::opj_image_t,
+        codec: *mut opj::opj_codec_t,
+        stream: *mut opj::opj_stream_t,
+    }
+
+    impl Drop for Handles {
+        fn drop(&mut self) {
+            unsafe {
+                if !self.stream.is_null() {
+                    opj::opj_stream_destroy(self.stream);
+                }
+                if !self.codec.is_null() {
+                    opj::opj_destroy_codec(self.codec);
+                }
+                if !self.image.is_null() {
+                    opj::opj_image_destroy(self.image);
+                }
+            }
+        }
+    }
+
+    unsafe extern "C" fn write_fn(buffer: *mut c_void, len: usize, user: *mut c_void) -> usize {
+        let out = &mut *(user as *mut Cursor<Vec<u8>>);
+        let buffer = std::slice::from_raw_parts(buffer as *const u8, len);
+        out.write(buffer).unwrap_or(usize::MAX)
+    }
+
+    unsafe extern "C" fn skip_fn(len: i64, user: *mut c_void) -> i64 {
+        let out = &mut *(user as *mut Cursor<Vec<u8>>);
+        match out.seek(SeekFrom::Current(len)) {
+            Ok(_) => len,
+            Err(_) => -1,
+        }
+    }
+
+    unsafe extern "C" fn seek_fn(pos: i64, user: *mut c_void) -> i32 {
+        let out = &mut *(user as *mut Cursor<Vec<u8>>);
+        out.seek(SeekFrom::Start(pos as u64)).is_ok() as i32
+    }
+
+    pub(super) fn encode(img: &DynamicImage) -> Result<Vec<u8>> {
+        let (width, height) = (img.width(), img.height());
+        let (components, precision, samples) = samples(img);
+
+        let mut out = Cursor::new(Vec::new());
+
+        unsafe {
+            let mut params: opj::opj_cparameters_t = std::mem::zeroed();
+            opj::opj_set_default_encoder_parameters(&mut params);
+
+            // A single lossless quality layer
+            params.tcp_numlayers = 1;
+            params.tcp_rates[0] = 0.0;
+            params.cp_disto_alloc = 1;
+
+            // Every resolution level halves the image, small images support fewer of them
+            while params.numresolution > 1 && 1u32 << (params.numresolution - 1) > width.min(height)
+            {
+                params.numresolution -= 1;
+            }
+
+            let mut component_params = vec![
+                opj::opj_image_cmptparm_t {
+                    dx: 1,
+                    dy: 1,
+                    w: width,
+                    h: height,
+                    x0: 0,
+                    y0: 0,
+                    prec: precision,
+                    bpp: precision,
+                    sgnd: 0,
+                };
+                components
+            ];
+
+            let color_space = if components < 3 {
+                opj::COLOR_SPACE::OPJ_CLRSPC_GRAY
+            } else {
+                opj::COLOR_SPACE::OPJ_CLRSPC_SRGB
+            };
+
+            let mut handles = Handles {
+                image: opj::opj_image_create(
+                    components as u32,
+                    component_params.as_mut_ptr(),
+                    color_space,
+                ),
+                codec: std::ptr::null_mut(),
+                stream: std::ptr::null_mut(),
+            };
+
+            if handles.image.is_null() {
+                return Err(VortexError::Encode(
+                    "Failed to allocate JPEG 2000 image".into(),
+                ));
+            }
+
+            let image = &mut *handles.image;
+            image.x0 = 0;
+            image.y0 = 0;
+            image.x1 = width;
+            image.y1 = height;
+
+            let pixels = width as usize * height as usize;
+
+            for c in 0..components {
+                let component = &mut *image.comps.add(c);
+
+                if (c == 1 && components == 2) || c == 3 {
+                    component.alpha = 1;
+                }
+
+                let data = std::slice::from_raw_parts_mut(component.data, pixels);
+                for (d, &s) in data
+                    .iter_mut()
+                    .zip(samples.iter().skip(c).step_by(components))
+                {
+                    *d = s;
+                }
+            }
+
+            handles.codec = opj::opj_create_compress(opj::CODEC_FORMAT::OPJ_CODEC_JP2);
+
+            if handles.codec.is_null()
+                || opj::opj_setup_encoder(handles.codec, &mut params, handles.image) == 0
+            {
+                return Err(VortexError::Encode(
+                    "Failed to set up JPEG 2000 encoder".into(),
+                ));
+            }
+
+            handles.stream = opj::opj_stream_create(1 << 20, 0);
+
+            if handles.stream.is_null() {
+                return Err(VortexError::Encode(
+                    "Failed to allocate JPEG 2000 stream".into(),
+                ));
+            }
+
+            opj::opj_stream_set_write_function(handles.stream, Some(write_fn));
+            opj::opj_stream_set_skip_function(handles.stream, Some(skip_fn));
+            opj::opj_stream_set_seek_function(handles.stream, Some(seek_fn));
+            opj::opj_stream_set_user_data(
+                handles.stream,
+                &mut out as *mut Cursor<Vec<u8>> as *mut c_void,
+                None,
+            );
+
+            let encoded = opj::opj_start_compress(handles.codec, handles.image, handles.stream)
+                != 0
+                && opj::opj_encode(handles.codec, handles.stream) != 0
+                && opj::opj_end_compress(handles.codec, handles.stream) != 0;
+
+            // The stream holds a pointer to `out`, release it before taking the data
+            drop(handles);
+
+            if !encoded {
+                return Err(VortexError::Encode(
+                    "Failed to encode JPEG 2000 image".into(),
+                ));
+            }
+        }
+
+        Ok(out.into_inner())
+    }
+}
+
+#[cfg(all(test, feature = "jpeg2000"))]
+mod tests {
+    use image::{DynamicImage, GrayImage, RgbImage};
+    use openjpeg_sys as opj;
+    use std::ffi::c_void;
+    use std::io::{Cursor, Read, Seek, SeekFrom};
+
+    unsafe extern "C" fn read_fn(buffer: *mut c_void, len: usize, user: *mut c_void) -> usize {
+        let input = &mut *(user as *mut Cursor<&[u8]>);
+        let buffer = std::slice::from_raw_parts_mut(buffer as *mut u8, len);
+        match input.read(buffer) {
+            Ok(0) | Err(_) => usize::MAX,
+            Ok(n) => n,
+        }
+    }
+
+    unsafe extern "C" fn skip_fn(len: i64, user: *mut c_void) -> i64 {
+        let input = &mut *(user as *mut Cursor<&[u8]>);
+        match input.seek(SeekFrom::Current(len)) {
+            Ok(_) => len,
+            Err(_) => -1,
+        }
+    }
+
+    unsafe extern "C" fn seek_fn(pos: i64, user: *mut c_void) -> i32 {
+        let input = &mut *(user as *mut Cursor<&[u8]>);
+        input.seek(SeekFrom::Start(pos as u64)).is_ok() as i32
+    }
+
+    /// Dimensions and per component samples of a JP2 file
+    fn decode(data: &[u8]) -> (u32, u32, Vec<Vec<i32>>) {
+        let mut input = Cursor::new(data);
+
+        unsafe {
+            let stream = opj::opj_stream_create(1 << 16, 1);
+            opj::opj_stream_set_read_function(stream, Some(read_fn));
+            opj::opj_stream_set_skip_function(stream, Some(skip_fn));
+            opj::opj_stream_set_seek_function(stream, Some(seek_fn));
+            opj::opj_stream_set_user_data(
+                stream,
+                &mut input as *mut Cursor<&[u8]> as *mut c_void,
+                None,
+            );
+            opj::opj_stream_set_user_data_length(stream, data.len() as u64);
+
+            let codec = opj::opj_create_decompress(opj::CODEC_FORMAT::OPJ_CODEC_JP2);
+            let mut params: opj::opj_dparameters_t = std::mem::zeroed();
+            opj::opj_set_default_decoder_parameters(&mut params);
+            assert_ne!(opj::opj_setup_decoder(codec, &mut params), 0);
+
+            let mut image = std::ptr::null_mut();
+            assert_ne!(opj::opj_read_header(stream, codec, &mut image), 0);
+            assert_ne!(opj::opj_decode(codec, stream, image), 0);
+
+            let img = &*image;
+            let components = (0..img.numcomps as usize)
+                .map(|c| {
+                    let comp = &*img.comps.add(c);
+                    std::slice::from_raw_parts(comp.data, (comp.w * comp.h) as usize).to_vec()
+                })
+                .collect();
+            let dimensions = (img.x1 - img.x0, img.y1 - img.y0);
+
+            opj::opj_image_destroy(image);
+            opj::opj_destroy_codec(codec);
+            opj::opj_stream_destroy(stream);
+
+            (dimensions.0, dimensions.1, components)
+        }
+    }
+
+    fn encode(img: &DynamicImage) -> Vec<u8> {
+        let mut out = Cursor::new(vec![]);
+        super::write(img, &mut out).unwrap();
+        out.into_inner()
+    }
+
+    #[test]
+    fn gray_round_trip() {
+        let pixels = vec![0, 50, 100, 150, 200, 255];
+        let img = DynamicImage::ImageLuma8(GrayImage::from_raw(3, 2, pixels.clone()).unwrap());
+
+        let (width, height, components) = decode(&encode(&img));
+
+        assert_eq!((width, height), (3, 2));
+        assert_eq!(components.len(), 1);
+        assert_eq!(
+            components[0],
+            pixels.into_iter().map(i32::from).collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn rgb_round_trip() {
+        let pixels = vec![255, 0, 0, 0, 255, 0, 0, 0, 255, 10, 20, 30];
+        let img = DynamicImage::ImageRgb8(RgbImage::from_raw(2, 2, pixels.clone()).unwrap());
+
+        let (width, height, components) = decode(&encode(&img));
+
+        assert_eq!((width, height), (2, 2));
+        assert_eq!(components.len(), 3);
+        for (c, component) in components.iter().enumerate() {
+            let expected = pixels.iter().skip(c).step_by(3).map(|&s| i32::from(s));
+            assert_eq!(component, &expected.collect::<Vec<_>>());
+        }
+    }
+}
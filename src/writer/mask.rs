@@ -0,0 +1,159 @@
+use super::{color, samples};
+use crate::{Mask, RawImage, Result, VortexError};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GrayImage, Rgba, RgbaImage};
+
+/// Combine the image with its mask into an RGBA image, images without a mask are returned as is.
+///
+/// Stencil images (`ImageMask true`) become black where they paint and transparent elsewhere,
+/// which is what a viewer shows with the default fill color.
+pub(crate) fn apply_mask(img: DynamicImage, raw: &RawImage) -> Result<DynamicImage> {
+    let (width, height) = (img.width(), img.height());
+
+    if raw.image_dict.image_mask {
+        return Ok(stencil(&img));
+    }
+
+    let alpha = match raw.mask {
+        Some(Mask::Soft(ref mask)) => resize(
+            color::to_dynamic_image(mask)?.to_luma8(),
+            width,
+            height,
+            FilterType::Triangle,
+        ),
+        Some(Mask::Stencil(ref mask)) => {
+            let mut stencil = color::to_dynamic_image(mask)?.to_luma8();
+            // Samples of 0 are painted through the stencil
+            imageops::invert(&mut stencil);
+            resize(stencil, width, height, FilterType::Nearest)
+        }
+        Some(Mask::ColorKey(ref ranges)) => color_key(
+            raw,
+            raw.image_dict.bits_per_component.unwrap_or(8) as usize,
+            width,
+            height,
+            ranges,
+        )?,
+        None => return Ok(img),
+    };
+
+    Ok(with_alpha(img, &alpha))
+}
+
+/// Black where the stencil `img` paints, samples of 0 after `Decode`, transparent elsewhere
+fn stencil(img: &DynamicImage) -> DynamicImage {
+    let mut alpha = img.to_luma8();
+    imageops::invert(&mut alpha);
+
+    let rgba = RgbaImage::from_fn(img.width(), img.height(), |x, y| {
+        Rgba([0, 0, 0, alpha.get_pixel(x, y).0[0]])
+    });
+
+    DynamicImage::ImageRgba8(rgba)
+}
+
+/// `img` with `alpha` of the same dimensions as its alpha channel
+fn with_alpha(img: DynamicImage, alpha: &GrayImage) -> DynamicImage {
+    let mut rgba = img.to_rgba8();
+
+    for (pixel, a) in rgba.pixels_mut().zip(alpha.pixels()) {
+        pixel.0[3] = a.0[0];
+    }
+
+    DynamicImage::ImageRgba8(rgba)
+}
+
+fn resize(mask: GrayImage, width: u32, height: u32, filter: FilterType) -> GrayImage {
+    if mask.dimensions() == (width, height) {
+        mask
+    } else {
+        imageops::resize(&mask, width, height, filter)
+    }
+}
+
+/// Alpha channel that hides every pixel whose raw `bpc` bit samples all fall inside `ranges`
+fn color_key(
+    data: &[u8],
+    bpc: usize,
+    width: u32,
+    height: u32,
+    ranges: &[u32],
+) -> Result<GrayImage> {
+    let n = ranges.len() / 2;
+
+    if n == 0 {
+        return Err(VortexError::InvalidImage("Empty color key mask"));
+    }
+
+    let samples: Vec<u32> = match bpc {
+        16 => samples::to_u16(data).into_iter().map(u32::from).collect(),
+        1 | 2 | 4 | 8 => samples::unpack(data, bpc, width as usize * n, height as usize, false)
+            .into_iter()
+            .map(u32::from)
+            .collect(),
+        _ => return Err(VortexError::UnsupportedBitDepth(bpc as i32)),
+    };
+
+    let alpha = samples
+        .chunks_exact(n)
+        .map(|pixel| {
+            let masked = pixel
+                .iter()
+                .zip(ranges.chunks_exact(2))
+                .all(|(&s, range)| range[0] <= s && s <= range[1]);
+            if masked {
+                0
+            } else {
+                255
+            }
+        })
+        .collect();
+
+    match GrayImage::from_raw(width, height, alpha) {
+        Some(alpha) => Ok(alpha),
+        None => Err(VortexError::InvalidImage(color::SHORT_DATA)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Luma;
+
+    #[test]
+    fn color_key_ranges() {
+        // RGB pixels, the second and third fall inside the key in every component
+        let data = [10, 20, 30, 1, 2, 3, 5, 5, 5, 1, 200, 3];
+        let ranges = [0, 5, 0, 5, 0, 5];
+
+        let alpha = color_key(&data, 8, 4, 1, &ranges).unwrap();
+        assert_eq!(alpha.into_raw(), [255, 0, 0, 255]);
+
+        // 4 bit gray samples
+        let alpha = color_key(&[0x3c], 4, 2, 1, &[2, 4]).unwrap();
+        assert_eq!(alpha.into_raw(), [0, 255]);
+
+        assert!(color_key(&data, 8, 4, 1, &[]).is_err());
+        assert!(color_key(&data, 8, 4, 2, &ranges).is_err());
+    }
+
+    #[test]
+    fn stencil_paints_zero_samples() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_raw(2, 1, vec![0, 255]).unwrap());
+
+        let rgba = stencil(&img).to_rgba8();
+        assert_eq!(rgba.into_raw(), [0, 0, 0, 255, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn soft_mask_of_another_size() {
+        let img = DynamicImage::ImageLuma8(GrayImage::from_pixel(4, 2, Luma([50])));
+        let mask = GrayImage::from_pixel(2, 1, Luma([100]));
+
+        let alpha = resize(mask, 4, 2, FilterType::Triangle);
+        assert_eq!(alpha.dimensions(), (4, 2));
+
+        let rgba = with_alpha(img, &alpha).to_rgba8();
+        assert!(rgba.pixels().all(|p| p.0 == [50, 50, 50, 100]));
+    }
+}
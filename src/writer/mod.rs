@@ -1,12 +1,81 @@
+mod color;
 pub mod io;
-use crate::{ImageFormat, RawImage, Result};
-use image::{ImageBuffer, RgbImage};
+mod jp2k;
+mod mask;
+mod samples;
+mod tif;
+use crate::{AvifOptions, ImageFormat, PngCompression, RawImage, Result, WebPMode};
+use image::codecs::{png, webp};
+use image::{DynamicImage, ImageEncoder};
 use std::io::{Seek, Write};
 
 fn get_image_dimensions(img: &RawImage) -> (u32, u32) {
     (img.image_dict.width, img.image_dict.height)
 }
 
+fn to_8_bit(img: DynamicImage) -> DynamicImage {
+    use DynamicImage::*;
+    match img {
+        ImageLuma16(_) => ImageLuma8(img.to_luma8()),
+        ImageLumaA16(_) => ImageLumaA8(img.to_luma_alpha8()),
+        ImageRgb16(_) => ImageRgb8(img.to_rgb8()),
+        ImageRgba16(_) => ImageRgba8(img.to_rgba8()),
+        img => img,
+    }
+}
+
+fn to_rgb(img: DynamicImage) -> DynamicImage {
+    use DynamicImage::*;
+    match img {
+        ImageLuma8(_) | ImageLuma16(_) => ImageRgb8(img.to_rgb8()),
+        ImageLumaA8(_) | ImageLumaA16(_) => ImageRgba8(img.to_rgba8()),
+        img => img,
+    }
+}
+
+fn write_png<W: Write>(img: &DynamicImage, w: W, compression: PngCompression) -> Result<()> {
+    let compression = match compression {
+        PngCompression::Fast => png::CompressionType::Fast,
+        PngCompression::Default => png::CompressionType::Default,
+        PngCompression::Best => png::CompressionType::Best,
+    };
+
+    png::PngEncoder::new_with_quality(w, compression, png::FilterType::Adaptive).write_image(
+        img.as_bytes(),
+        img.width(),
+        img.height(),
+        img.color(),
+    )?;
+    Ok(())
+}
+
+fn write_webp<W: Write>(img: &DynamicImage, w: W, mode: WebPMode) -> Result<()> {
+    let quality = match mode {
+        WebPMode::Lossy(quality) => webp::WebPQuality::lossy(quality),
+        WebPMode::Lossless => webp::WebPQuality::lossless(),
+    };
+
+    webp::WebPEncoder::new_with_quality(w, quality).write_image(
+        img.as_bytes(),
+        img.width(),
+        img.height(),
+        img.color(),
+    )?;
+    Ok(())
+}
+
+#[cfg(feature = "avif")]
+fn write_avif<W: Write>(img: &DynamicImage, w: W, options: AvifOptions) -> Result<()> {
+    image::codecs::avif::AvifEncoder::new_with_speed_quality(w, options.speed, options.quality)
+        .write_image(img.as_bytes(), img.width(), img.height(), img.color())?;
+    Ok(())
+}
+
+#[cfg(not(feature = "avif"))]
+fn write_avif<W: Write>(_img: &DynamicImage, _w: W, _options: AvifOptions) -> Result<()> {
+    Err(crate::VortexError::missing_feature("avif", "avif"))
+}
+
 pub trait OutputWriter<R: Write + Seek> {
     fn write_to(&mut self, w: R) -> Result<()>;
 }
@@ -24,17 +93,46 @@ impl<'a> ImageWriter<'a> {
 
 impl<R: Write + Seek> OutputWriter<R> for ImageWriter<'_> {
     fn write_to(&mut self, mut w: R) -> Result<()> {
-        let (width, height) = get_image_dimensions(&self.image);
+        let (width, height) = get_image_dimensions(self.image);
+
+        if let Some(encoded) = self.image.passthrough(self.img_format) {
+            log::info!("image dimensions W : {width} H : {height} written without re-encoding");
+            w.write_all(&encoded.data)?;
+            return Ok(());
+        }
 
-        let mut img: RgbImage =
-            ImageBuffer::new(self.image.image_dict.width, self.image.image_dict.height);
         log::info!(
-            "image dimensions W : {width} H : {height} Total pixels : {} Raw Image Size {}",
+            "image dimensions W : {width} H : {height} Total pixels : {} Raw Image Size {} Color space {:?}",
             width * height,
-            img.len()
+            self.image.len(),
+            self.image.image_dict.color_space
         );
-        img.copy_from_slice(&self.image);
-        img.write_to(&mut w, self.img_format)?;
+
+        let format = self.img_format.for_image(self.image);
+
+        let mut img = color::to_dynamic_image(self.image)?;
+
+        if format.supports_alpha() {
+            img = mask::apply_mask(img, self.image)?;
+        }
+
+        if !format.supports_gray() {
+            img = to_rgb(img);
+        }
+
+        if !format.supports_16_bit() {
+            img = to_8_bit(img);
+        }
+
+        match format {
+            ImageFormat::Jp2k => jp2k::write(&img, &mut w)?,
+            ImageFormat::Tiff(compression) => tif::write(&img, &mut w, compression)?,
+            ImageFormat::Png(compression) => write_png(&img, &mut w, compression)?,
+            ImageFormat::WebP(mode) => write_webp(&img, &mut w, mode)?,
+            ImageFormat::Avif(options) => write_avif(&img, &mut w, options)?,
+            _ => img.write_to(&mut w, format)?,
+        }
+
         Ok(())
     }
 }
@@ -0,0 +1,89 @@
+/// Number of bytes taken by `rows` rows of `samples_per_row` samples, every row
+/// starts on a byte boundary
+pub(crate) fn packed_len(samples_per_row: usize, rows: usize, bpc: usize) -> usize {
+    (samples_per_row * bpc).div_ceil(8) * rows
+}
+
+/// Expand `bpc` bit samples into one byte per sample.
+///
+/// With `scale` the values are stretched to the full `0..=255` range, otherwise
+/// they are kept as is, e.g. for indexes into a color table.
+pub(crate) fn unpack(
+    data: &[u8],
+    bpc: usize,
+    samples_per_row: usize,
+    rows: usize,
+    scale: bool,
+) -> Vec<u8> {
+    let row_len = packed_len(samples_per_row, 1, bpc);
+
+    // Empty rows hold no samples
+    if row_len == 0 {
+        return vec![];
+    }
+
+    let max = (1u16 << bpc) - 1;
+
+    let mut out = Vec::with_capacity(samples_per_row * rows);
+
+    for row in data.chunks(row_len).take(rows) {
+        for i in 0..samples_per_row {
+            let bit = i * bpc;
+            let byte = row.get(bit / 8).copied().unwrap_or(0);
+            let value = (byte >> (8 - bpc - bit % 8)) as u16 & max;
+
+            out.push(if scale { value * 255 / max } else { value } as u8);
+        }
+    }
+
+    out
+}
+
+/// Big endian 16 bit samples as native integers
+pub(crate) fn to_u16(data: &[u8]) -> Vec<u16> {
+    data.chunks_exact(2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .collect()
+}
+
+/// Reduce big endian 16 bit samples to 8 bits by keeping the high byte
+pub(crate) fn to_u8(data: &[u8]) -> Vec<u8> {
+    data.chunks_exact(2).map(|b| b[0]).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unpack_bits() {
+        // Rows start on a byte boundary, the padding bits are dropped
+        assert_eq!(
+            unpack(&[0b1010_0000, 0b0110_0000], 1, 3, 2, true),
+            [255, 0, 255, 0, 255, 255]
+        );
+        assert_eq!(unpack(&[0b0001_1011], 2, 4, 1, true), [0, 85, 170, 255]);
+        assert_eq!(unpack(&[0b0001_1011], 2, 4, 1, false), [0, 1, 2, 3]);
+        assert_eq!(unpack(&[0x0f, 0x70], 4, 3, 1, false), [0, 15, 7]);
+        assert_eq!(unpack(&[0x0f, 0x70], 4, 3, 1, true), [0, 255, 119]);
+    }
+
+    #[test]
+    fn sixteen_bit() {
+        let data = [0x12, 0x34, 0xff, 0x00];
+        assert_eq!(to_u16(&data), [0x1234, 0xff00]);
+        assert_eq!(to_u8(&data), [0x12, 0xff]);
+    }
+
+    #[test]
+    fn packed_rows() {
+        assert_eq!(packed_len(3, 2, 1), 2);
+        assert_eq!(packed_len(5, 3, 4), 9);
+        assert_eq!(packed_len(2, 2, 16), 8);
+    }
+
+    #[test]
+    fn empty_rows() {
+        assert!(unpack(&[0xff], 1, 0, 4, true).is_empty());
+    }
+}
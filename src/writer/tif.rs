@@ -0,0 +1,73 @@
+use crate::{Result, TiffCompression};
+use image::DynamicImage;
+use std::io::{Seek, Write};
+use tiff::encoder::colortype::{self, ColorType};
+use tiff::encoder::compression::{Compression, Deflate, Lzw, Uncompressed};
+use tiff::encoder::TiffEncoder;
+
+/// Write `img` as a TIFF file, `image` itself only writes uncompressed TIFF
+pub(crate) fn write<W: Write + Seek>(
+    img: &DynamicImage,
+    w: &mut W,
+    compression: TiffCompression,
+) -> Result<()> {
+    match compression {
+        TiffCompression::None => write_with(img, w, Uncompressed),
+        TiffCompression::Lzw => write_with(img, w, Lzw),
+        TiffCompression::Deflate => write_with(img, w, Deflate::default()),
+    }
+}
+
+fn write_with<W: Write + Seek, D: Compression>(
+    img: &DynamicImage,
+    w: &mut W,
+    compression: D,
+) -> Result<()> {
+    use DynamicImage::*;
+
+    let mut encoder = TiffEncoder::new(w)?;
+    let (width, height) = (img.width(), img.height());
+
+    match img {
+        ImageLuma8(buf) => {
+            encode::<_, colortype::Gray8, _>(&mut encoder, width, height, compression, buf)
+        }
+        ImageLuma16(buf) => {
+            encode::<_, colortype::Gray16, _>(&mut encoder, width, height, compression, buf)
+        }
+        ImageRgb16(buf) => {
+            encode::<_, colortype::RGB16, _>(&mut encoder, width, height, compression, buf)
+        }
+        ImageRgba16(buf) => {
+            encode::<_, colortype::RGBA16, _>(&mut encoder, width, height, compression, buf)
+        }
+        img if img.color().has_alpha() => encode::<_, colortype::RGBA8, _>(
+            &mut encoder,
+            width,
+            height,
+            compression,
+            &img.to_rgba8(),
+        ),
+        img => encode::<_, colortype::RGB8, _>(
+            &mut encoder,
+            width,
+            height,
+            compression,
+            &img.to_rgb8(),
+        ),
+    }
+}
+
+fn encode<W: Write + Seek, C: ColorType, D: Compression>(
+    encoder: &mut TiffEncoder<W>,
+    width: u32,
+    height: u32,
+    compression: D,
+    data: &[C::Inner],
+) -> Result<()>
+where
+    [C::Inner]: tiff::encoder::TiffValue,
+{
+    encoder.write_image_with_compression::<C, D>(width, height, compression, data)?;
+    Ok(())
+}
@@ -0,0 +1,1039 @@
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use vortex::extractor::{
+    extract_images, extract_images_with, extract_images_with_report, list_images,
+    par_extract_images, par_for_each_image, Dedupe, ExtractOptions, ImageIter, Method, PageRange,
+};
+use vortex::manifest::{Manifest, ManifestEntry};
+use vortex::template::{NameFields, NameTemplate};
+use vortex::writer::create_output_writer;
+use vortex::writer::io::{write_atomic, OverwritePolicy};
+use vortex::{ImageFormat, PngCompression, RawImage, VortexError, WebPMode};
+
+const SAMPLES: [(&str, &[u8]); 3] = [
+    ("sample.pdf", include_bytes!("../resources/sample.pdf")),
+    ("sample2.pdf", include_bytes!("../resources/sample2.pdf")),
+    ("sample3.pdf", include_bytes!("../resources/sample3.pdf")),
+];
+
+fn sample_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join(name)
+}
+
+fn sorted(images: &[RawImage]) -> Vec<&RawImage> {
+    let mut images = images.iter().collect::<Vec<_>>();
+    images.sort_by(|a, b| {
+        (a.image_dict.width, a.image_dict.height, &a[..]).cmp(&(
+            b.image_dict.width,
+            b.image_dict.height,
+            &b[..],
+        ))
+    });
+    images
+}
+
+fn assert_same_images(expected: &[RawImage], actual: &[RawImage]) {
+    assert_eq!(expected.len(), actual.len());
+
+    for (a, b) in sorted(expected).into_iter().zip(sorted(actual)) {
+        assert_eq!(a.image_dict.width, b.image_dict.width);
+        assert_eq!(a.image_dict.height, b.image_dict.height);
+        assert_eq!(&a[..], &b[..]);
+    }
+}
+
+/// Minimal pdf made of `objects`, numbered from 1. The first object is the catalog.
+fn build_pdf(objects: &[Vec<u8>]) -> Vec<u8> {
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend(format!("{} 0 obj\n", i + 1).as_bytes());
+        pdf.extend(object);
+        pdf.extend(b"\nendobj\n");
+    }
+
+    let xref = pdf.len();
+    pdf.extend(format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).as_bytes());
+    for offset in offsets {
+        pdf.extend(format!("{offset:010} 00000 n \n").as_bytes());
+    }
+    pdf.extend(
+        format!(
+            "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+            objects.len() + 1
+        )
+        .as_bytes(),
+    );
+
+    pdf
+}
+
+fn object(dict: &str) -> Vec<u8> {
+    dict.as_bytes().to_vec()
+}
+
+fn stream(dict: &str, data: &[u8]) -> Vec<u8> {
+    let mut object = format!("<< {dict} /Length {} >>\nstream\n", data.len()).into_bytes();
+    object.extend(data);
+    object.extend(b"\nendstream");
+    object
+}
+
+/// 2x2 gray image XObject
+fn gray_image(data: &[u8; 4]) -> Vec<u8> {
+    stream(
+        "/Type /XObject /Subtype /Image /Width 2 /Height 2 /ColorSpace /DeviceGray \
+         /BitsPerComponent 8",
+        data,
+    )
+}
+
+/// Catalog and page tree for pages `3 0 R` to `2 + count 0 R`
+fn page_tree(count: usize) -> Vec<Vec<u8>> {
+    let kids = (0..count)
+        .map(|i| format!("{} 0 R", i + 3))
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    vec![
+        object("<< /Type /Catalog /Pages 2 0 R >>"),
+        object(&format!("<< /Type /Pages /Kids [{kids}] /Count {count} >>")),
+    ]
+}
+
+fn page(resources: &str, contents: usize) -> Vec<u8> {
+    object(&format!(
+        "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 10 10] /Resources << {resources} >> \
+         /Contents {contents} 0 R >>"
+    ))
+}
+
+/// One page drawing `image`, object `5 0 R`, as `/Im1`
+fn image_pdf(image: Vec<u8>) -> Vec<u8> {
+    let mut objects = page_tree(1);
+    objects.extend([
+        page("/XObject << /Im1 5 0 R >>", 4),
+        stream("", b"q 2 0 0 2 0 0 cm /Im1 Do Q"),
+        image,
+    ]);
+    build_pdf(&objects)
+}
+
+/// 2x2 gray JPEG file
+fn gray_jpeg() -> Vec<u8> {
+    let mut jpeg = vec![];
+    image::codecs::jpeg::JpegEncoder::new(&mut jpeg)
+        .encode(&[0, 64, 128, 255], 2, 2, image::ColorType::L8)
+        .unwrap();
+    jpeg
+}
+
+/// `data` stored uncompressed in a zlib stream of one block
+fn zlib(data: &[u8]) -> Vec<u8> {
+    let len = u16::try_from(data.len()).unwrap();
+
+    let mut zlib = vec![0x78, 0x01, 1];
+    zlib.extend(len.to_le_bytes());
+    zlib.extend((!len).to_le_bytes());
+    zlib.extend(data);
+
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    zlib.extend(((b << 16) | a).to_be_bytes());
+    zlib
+}
+
+fn jpeg_image(jpeg: &[u8]) -> Vec<u8> {
+    stream(
+        "/Type /XObject /Subtype /Image /Width 2 /Height 2 /ColorSpace /DeviceGray \
+         /BitsPerComponent 8 /Filter /DCTDecode",
+        jpeg,
+    )
+}
+
+#[test]
+fn bytes_match_file() {
+    for (name, bytes) in SAMPLES {
+        let from_file = extract_images(Method::File(sample_path(name))).unwrap();
+        let from_bytes = extract_images(Method::Bytes(bytes)).unwrap();
+
+        assert!(!from_file.is_empty(), "{name} has no images");
+        assert_same_images(&from_file, &from_bytes);
+    }
+}
+
+#[test]
+fn owned_and_shared_buffers() {
+    for (name, bytes) in SAMPLES {
+        let from_slice = extract_images(bytes.into()).unwrap();
+        let from_vec = extract_images(bytes.to_vec().into()).unwrap();
+        let from_arc = extract_images(Arc::<[u8]>::from(bytes).into()).unwrap();
+
+        assert!(!from_slice.is_empty(), "{name} has no images");
+        assert_same_images(&from_slice, &from_vec);
+        assert_same_images(&from_slice, &from_arc);
+    }
+}
+
+#[test]
+fn invalid_bytes_error() {
+    assert!(matches!(
+        extract_images(Method::Bytes(b"not a pdf")),
+        Err(VortexError::Parse(_))
+    ));
+}
+
+#[test]
+fn errors_are_send_and_sync() {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<VortexError>();
+
+    assert!(matches!(
+        "jpeg:200".parse::<ImageFormat>(),
+        Err(VortexError::InvalidFormat(_))
+    ));
+    assert!(matches!(
+        "3-1".parse::<PageRange>(),
+        Err(VortexError::InvalidPageRange(_))
+    ));
+}
+
+#[test]
+fn reader_matches_bytes() {
+    for (_, bytes) in SAMPLES {
+        let from_bytes = extract_images(Method::Bytes(bytes)).unwrap();
+        let from_reader = extract_images(Method::Reader(Box::new(bytes))).unwrap();
+
+        assert_same_images(&from_bytes, &from_reader);
+    }
+}
+
+#[test]
+fn iter_decodes_lazily() {
+    // The image on page 2 fails to decode
+    let mut objects = page_tree(2);
+    objects.extend([
+        page("/XObject << /Im1 7 0 R >>", 5),
+        page("/XObject << /Im1 8 0 R >>", 6),
+        stream("", b"/Im1 Do"),
+        stream("", b"/Im1 Do"),
+        gray_image(&[0, 64, 128, 255]),
+        stream(
+            "/Type /XObject /Subtype /Image /Width 2 /Height 2 /ColorSpace /DeviceGray \
+             /BitsPerComponent 8 /Filter /FlateDecode",
+            b"definitely not zlib data",
+        ),
+    ]);
+    let pdf = build_pdf(&objects);
+
+    let mut iter = ImageIter::new(Method::Bytes(&pdf)).unwrap();
+
+    let first = iter.next().unwrap().unwrap();
+    assert_eq!(first.object_id, Some(7));
+    assert_eq!(iter.report().extracted, 1);
+    assert!(iter.report().failures.is_empty());
+
+    assert!(matches!(
+        iter.next(),
+        Some(Err(VortexError::Decode {
+            page: Some(2),
+            object_id: Some(8),
+            ..
+        }))
+    ));
+}
+
+#[test]
+fn page_range_selects_subset() {
+    let (_, bytes) = SAMPLES[0];
+
+    let all = extract_images(Method::Bytes(bytes)).unwrap();
+
+    let options = ExtractOptions {
+        pages: Some("1".parse().unwrap()),
+        ..Default::default()
+    };
+    let first = extract_images_with(Method::Bytes(bytes), &options).unwrap();
+
+    let on_first_page = all
+        .iter()
+        .filter(|img| img.page == Some(1))
+        .cloned()
+        .collect::<Vec<_>>();
+    assert!(!first.is_empty());
+    assert!(first.iter().all(|img| img.page == Some(1)));
+    assert_same_images(&on_first_page, &first);
+
+    let options = ExtractOptions {
+        pages: Some("1-".parse().unwrap()),
+        ..Default::default()
+    };
+    let open_ended = extract_images_with(Method::Bytes(bytes), &options).unwrap();
+
+    assert_same_images(&all, &open_ended);
+
+    let options = ExtractOptions {
+        pages: Some("1000-".parse().unwrap()),
+        ..Default::default()
+    };
+    assert!(matches!(
+        extract_images_with(Method::Bytes(bytes), &options),
+        Err(VortexError::InvalidPageRange(_))
+    ));
+}
+
+#[test]
+fn page_range_parsing() {
+    let range: PageRange = "1-5,9,20-".parse().unwrap();
+
+    assert!(range.contains(1) && range.contains(5) && range.contains(9));
+    assert!(!range.contains(6) && !range.contains(19));
+    assert!(range.contains(20) && range.contains(500));
+    assert_eq!(range.indices(10).unwrap(), vec![0, 1, 2, 3, 4, 8]);
+    assert_eq!(
+        "2-50".parse::<PageRange>().unwrap().indices(3).unwrap(),
+        vec![1, 2]
+    );
+    assert_eq!(range.indices(8).unwrap(), vec![0, 1, 2, 3, 4]);
+    assert_eq!(range.indices(3).unwrap(), vec![0, 1, 2]);
+    assert!("9,20-".parse::<PageRange>().unwrap().indices(8).is_err());
+
+    assert!("0".parse::<PageRange>().is_err());
+    assert!("5-2".parse::<PageRange>().is_err());
+    assert!("a-b".parse::<PageRange>().is_err());
+}
+
+#[test]
+fn format_options_parsing() {
+    assert!(matches!(
+        "jpeg:85".parse::<ImageFormat>(),
+        Ok(ImageFormat::Jpeg(85))
+    ));
+    assert!(matches!(
+        "png:compression=best".parse::<ImageFormat>(),
+        Ok(ImageFormat::Png(PngCompression::Best))
+    ));
+    assert!(matches!(
+        "webp:lossless".parse::<ImageFormat>(),
+        Ok(ImageFormat::WebP(WebPMode::Lossless))
+    ));
+    assert!(matches!(
+        "webp:quality=60".parse::<ImageFormat>(),
+        Ok(ImageFormat::WebP(WebPMode::Lossy(60)))
+    ));
+
+    assert!("jpeg:101".parse::<ImageFormat>().is_err());
+    assert!("png:compression=max".parse::<ImageFormat>().is_err());
+    assert!("bmp:85".parse::<ImageFormat>().is_err());
+}
+
+#[test]
+fn raw_mode_keeps_jpeg_streams() {
+    let options = ExtractOptions {
+        raw: true,
+        ..Default::default()
+    };
+
+    for (_, bytes) in SAMPLES {
+        for img in extract_images_with(Method::Bytes(bytes), &options).unwrap() {
+            match img.passthrough_extension() {
+                Some("jpg") => assert!(img.encoded.unwrap().data.starts_with(&[0xff, 0xd8])),
+                Some(_) => {}
+                None => assert!(!img.is_empty()),
+            }
+        }
+    }
+}
+
+#[cfg(not(feature = "jpeg2000"))]
+#[test]
+fn jp2k_needs_feature() {
+    match "jp2k".parse::<ImageFormat>() {
+        Err(VortexError::InvalidFormat(msg)) => assert!(msg.contains("jpeg2000"), "{msg}"),
+        _ => panic!("jp2k parsed without the jpeg2000 feature"),
+    }
+}
+
+#[cfg(feature = "jpeg2000")]
+#[test]
+fn jp2k_writes_jp2_files() {
+    let (_, bytes) = SAMPLES[0];
+    let img = &extract_images(Method::Bytes(bytes)).unwrap()[0];
+
+    let mut out = std::io::Cursor::new(vec![]);
+    create_output_writer(img, "jp2k".parse().unwrap())
+        .write_to(&mut out)
+        .unwrap();
+
+    assert!(out.into_inner().starts_with(b"\x00\x00\x00\x0cjP  "));
+}
+
+#[test]
+fn auto_format_copies_plain_jpegs() {
+    let options = ExtractOptions {
+        copy_jpeg: true,
+        ..Default::default()
+    };
+
+    for (_, bytes) in SAMPLES {
+        // Without copy_jpeg no stream is kept next to the pixels
+        for img in extract_images(Method::Bytes(bytes)).unwrap() {
+            assert!(img.encoded.is_none());
+            assert!(img
+                .passthrough(ImageFormat::Png(PngCompression::Best))
+                .is_none());
+        }
+
+        for img in extract_images_with(Method::Bytes(bytes), &options).unwrap() {
+            match img.output_extension(ImageFormat::Auto) {
+                "jpg" => {
+                    let encoded = img.passthrough(ImageFormat::Auto).unwrap();
+                    assert!(encoded.data.starts_with(&[0xff, 0xd8]));
+                    assert!(img.mask.is_none());
+                    assert!(img.is_empty(), "copied JPEG was decoded");
+                }
+                extension => assert!(["jpeg", "png", "tiff"].contains(&extension)),
+            }
+        }
+    }
+
+    let jpeg = gray_jpeg();
+    let pdf = image_pdf(jpeg_image(&jpeg));
+
+    let copied = &extract_images_with(Method::Bytes(&pdf), &options).unwrap()[0];
+    assert!(copied.is_empty());
+    assert_eq!(
+        &copied.passthrough(ImageFormat::Auto).unwrap().data[..],
+        &jpeg[..]
+    );
+
+    let decoded = &extract_images(Method::Bytes(&pdf)).unwrap()[0];
+    assert_eq!(decoded.len(), 4);
+    assert!(decoded.passthrough(ImageFormat::Auto).is_none());
+    assert_eq!(decoded.output_extension(ImageFormat::Auto), "jpeg");
+}
+
+#[test]
+fn lenient_report_counts_images() {
+    let options = ExtractOptions {
+        lenient: true,
+        ..Default::default()
+    };
+
+    for (_, bytes) in SAMPLES {
+        let strict = extract_images(Method::Bytes(bytes)).unwrap();
+        let (lenient, report) = extract_images_with_report(Method::Bytes(bytes), &options).unwrap();
+
+        assert!(report.failures.is_empty());
+        assert_eq!(report.extracted, lenient.len());
+        assert_same_images(&strict, &lenient);
+    }
+}
+
+#[test]
+fn lenient_skips_corrupted_images() {
+    let mut objects = page_tree(1);
+    objects.extend([
+        page("/XObject << /Im1 5 0 R /Im2 6 0 R >>", 4),
+        stream("", b"/Im1 Do /Im2 Do"),
+        gray_image(&[0, 64, 128, 255]),
+        stream(
+            "/Type /XObject /Subtype /Image /Width 2 /Height 2 /ColorSpace /DeviceGray \
+             /BitsPerComponent 8 /Filter /FlateDecode",
+            b"definitely not zlib data",
+        ),
+    ]);
+    let pdf = build_pdf(&objects);
+
+    match extract_images(Method::Bytes(&pdf)) {
+        Err(VortexError::Decode {
+            page: Some(1),
+            object_id: Some(6),
+            ..
+        }) => {}
+        Err(e) => panic!("unexpected error {e:?}"),
+        Ok(_) => panic!("corrupted image extracted"),
+    }
+
+    let options = ExtractOptions {
+        lenient: true,
+        ..Default::default()
+    };
+    let (images, report) = extract_images_with_report(Method::Bytes(&pdf), &options).unwrap();
+
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].object_id, Some(5));
+    assert_eq!(report.extracted, 1);
+    assert_eq!(report.failures.len(), 1);
+    assert!(matches!(
+        report.failures[0],
+        VortexError::Decode {
+            object_id: Some(6),
+            ..
+        }
+    ));
+
+    let parallel = par_for_each_image(Method::Bytes(&pdf), &options, |_, _| Ok(())).unwrap();
+    assert_eq!(parallel.extracted, 1);
+    assert_eq!(parallel.failures.len(), 1);
+}
+
+#[test]
+fn manifest_describes_images() {
+    let (_, bytes) = SAMPLES[0];
+    let mut manifest = Manifest::default();
+
+    for img in extract_images(Method::Bytes(bytes)).unwrap() {
+        assert!(img.page.is_some());
+
+        let entry = ManifestEntry::new(&img, Path::new("image.png"), b"data");
+        assert_eq!(entry.width, img.image_dict.width);
+        assert_eq!(entry.size, 4);
+        assert_eq!(
+            entry.sha256,
+            "3a6eb0790f39ac87c94f3856b2dd2c5d110e6811602261a9a923d3bb23adc8b7"
+        );
+
+        manifest.push(entry);
+    }
+
+    let mut json = vec![];
+    manifest.write_json(&mut json).unwrap();
+    assert!(String::from_utf8(json).unwrap().contains("\"sha256\""));
+
+    let mut csv = vec![];
+    manifest.write_csv(&mut csv).unwrap();
+    assert_eq!(
+        String::from_utf8(csv).unwrap().lines().count(),
+        manifest.entries.len() + 1
+    );
+
+    let pdf = image_pdf(jpeg_image(&gray_jpeg()));
+    let img = &extract_images(Method::Bytes(&pdf)).unwrap()[0];
+    let entry = ManifestEntry::new(img, Path::new("image.jpeg"), b"data");
+    assert_eq!(entry.xobject.as_deref(), Some("Im1"));
+    assert_eq!(entry.filters, ["DCTDecode"]);
+    assert_eq!(entry.color_space.as_deref(), Some("DeviceGray"));
+
+    // Raw mode lists every filter of the stream, not only the codec
+    let pdf = image_pdf(stream(
+        "/Type /XObject /Subtype /Image /Width 2 /Height 2 /ColorSpace /DeviceGray \
+         /BitsPerComponent 8 /Filter [/FlateDecode /DCTDecode]",
+        &zlib(&gray_jpeg()),
+    ));
+    let options = ExtractOptions {
+        raw: true,
+        ..Default::default()
+    };
+    let img = &extract_images_with(Method::Bytes(&pdf), &options).unwrap()[0];
+    assert_eq!(img.passthrough_extension(), Some("jpg"));
+    let entry = ManifestEntry::new(img, Path::new("image.jpg"), b"data");
+    assert_eq!(entry.filters, ["FlateDecode", "DCTDecode"]);
+
+    struct Broken;
+
+    impl std::io::Write for Broken {
+        fn write(&mut self, _: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    assert!(matches!(
+        manifest.write_json(Broken),
+        Err(VortexError::Io(_))
+    ));
+    assert!(matches!(
+        manifest.write_csv(Broken),
+        Err(VortexError::Io(_))
+    ));
+}
+
+#[test]
+fn list_matches_extracted_images() {
+    for (_, bytes) in SAMPLES {
+        let images = extract_images(Method::Bytes(bytes)).unwrap();
+        let infos = list_images(Method::Bytes(bytes), &ExtractOptions::default()).unwrap();
+
+        assert_eq!(images.len(), infos.len());
+
+        for (img, info) in images.iter().zip(&infos) {
+            assert_eq!(img.page, Some(info.page));
+            assert_eq!(img.object_id, info.object_id);
+            assert_eq!(
+                (img.image_dict.width, img.image_dict.height),
+                (info.width, info.height)
+            );
+            assert!(info.encoded_size > 0);
+        }
+    }
+}
+
+#[test]
+fn list_reports_stored_stream_size() {
+    let jpeg = gray_jpeg();
+
+    let mut objects = page_tree(1);
+    objects.extend([
+        page("/XObject << /Im1 5 0 R /Im2 6 0 R /Im3 7 0 R >>", 4),
+        stream("", b"/Im1 Do /Im2 Do /Im3 Do"),
+        gray_image(&[0, 64, 128, 255]),
+        jpeg_image(&jpeg),
+        // Never inflated, so listing does not notice it is broken
+        stream(
+            "/Type /XObject /Subtype /Image /Width 20 /Height 20 /ColorSpace /DeviceGray \
+             /BitsPerComponent 8 /Filter /FlateDecode",
+            b"definitely not zlib data",
+        ),
+    ]);
+    let pdf = build_pdf(&objects);
+
+    let infos = list_images(Method::Bytes(&pdf), &ExtractOptions::default()).unwrap();
+
+    let sizes = infos
+        .iter()
+        .map(|info| (info.xobject.as_deref().unwrap(), info.encoded_size))
+        .collect::<Vec<_>>();
+    assert_eq!(sizes, [("Im1", 4), ("Im2", jpeg.len()), ("Im3", 24)]);
+    assert_eq!(infos[2].filters, ["FlateDecode"]);
+}
+
+#[test]
+fn parallel_matches_sequential_order() {
+    for (_, bytes) in SAMPLES {
+        let sequential = extract_images(Method::Bytes(bytes)).unwrap();
+        let parallel =
+            par_extract_images(Method::Bytes(bytes), &ExtractOptions::default()).unwrap();
+
+        assert_eq!(sequential.len(), parallel.len());
+
+        for (a, b) in sequential.iter().zip(&parallel) {
+            assert_eq!(a.page, b.page);
+            assert_eq!(a.object_id, b.object_id);
+            assert_eq!(&a[..], &b[..]);
+        }
+
+        let report =
+            par_for_each_image(Method::Bytes(bytes), &ExtractOptions::default(), |_, _| {
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(report.extracted, sequential.len());
+    }
+}
+
+#[test]
+fn parallel_positions_span_page_chunks() {
+    // More pages than threads, each with an inline image and a shared XObject
+    const PAGES: usize = 20;
+
+    let mut objects = page_tree(PAGES);
+    let image = 3 + 2 * PAGES;
+
+    for k in 0..PAGES {
+        objects.push(page(
+            &format!("/XObject << /Im1 {image} 0 R >>"),
+            3 + PAGES + k,
+        ));
+    }
+    for k in 0..PAGES {
+        let mut contents = b"/Im1 Do BI /Width 2 /Height 2 /ColorSpace /DeviceGray \
+            /BitsPerComponent 8 ID "
+            .to_vec();
+        contents.extend([k as u8 + 1; 4]);
+        contents.extend(b" EI");
+        objects.push(stream("", &contents));
+    }
+    objects.push(gray_image(&[0, 64, 128, 255]));
+    let pdf = build_pdf(&objects);
+
+    let sequential = extract_images(Method::Bytes(&pdf)).unwrap();
+    assert_eq!(sequential.len(), 2 * PAGES);
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(2)
+        .build()
+        .unwrap();
+    let seen = std::sync::Mutex::new(vec![]);
+
+    let report = pool
+        .install(|| {
+            par_for_each_image(Method::Bytes(&pdf), &ExtractOptions::default(), |i, img| {
+                let name = NameTemplate::default()
+                    .render(&NameFields {
+                        doc: "doc",
+                        index: i,
+                        image: &img,
+                        extension: "png",
+                        data: &img,
+                    })
+                    .unwrap();
+                seen.lock()
+                    .unwrap()
+                    .push((i, img.page, img.page_index, img.to_vec(), name));
+                Ok(())
+            })
+        })
+        .unwrap();
+    assert_eq!(report.extracted, 2 * PAGES);
+
+    let mut seen = seen.into_inner().unwrap();
+    seen.sort_by_key(|&(i, ..)| i);
+
+    for (n, (img, (i, page, page_index, data, name))) in sequential.iter().zip(seen).enumerate() {
+        assert_eq!(i, n);
+        assert_eq!((page, page_index), (img.page, img.page_index));
+        assert_eq!(data, &img[..]);
+        assert_eq!(name, PathBuf::from(format!("extracted_image_{n}.png")));
+    }
+}
+
+#[test]
+fn name_templates() {
+    let (_, bytes) = SAMPLES[0];
+    let images = extract_images(Method::Bytes(bytes)).unwrap();
+    let img = &images[0];
+
+    let fields = NameFields {
+        doc: "sample",
+        index: 3,
+        image: img,
+        extension: "png",
+        data: b"data",
+    };
+
+    assert_eq!(
+        NameTemplate::default().render(&fields).unwrap(),
+        PathBuf::from("extracted_image_3.png")
+    );
+
+    let template: NameTemplate = "{doc}/page{page}/{page_index}_{width}x{height}.{ext}"
+        .parse()
+        .unwrap();
+    assert_eq!(
+        template.render(&fields).unwrap(),
+        PathBuf::from(format!(
+            "sample/page{}/{}_{}x{}.png",
+            img.page.unwrap(),
+            img.page_index,
+            img.image_dict.width,
+            img.image_dict.height
+        ))
+    );
+
+    assert!("{hash}".parse::<NameTemplate>().is_ok());
+    assert!("{nope}.{ext}".parse::<NameTemplate>().is_err());
+    assert!("{index".parse::<NameTemplate>().is_err());
+    assert!("../{index}.{ext}".parse::<NameTemplate>().is_err());
+    assert!("/tmp/{index}.{ext}".parse::<NameTemplate>().is_err());
+    assert!("./{index}.{ext}".parse::<NameTemplate>().is_err());
+
+    // Values naming the current or parent folder cannot leave the output folder
+    let mut dots = img.clone();
+    for name in ["..", ".", "...", "/..", "../.."] {
+        dots.name = Some(name.to_owned());
+        let fields = NameFields {
+            doc: "..",
+            image: &dots,
+            ..fields
+        };
+
+        let template: NameTemplate = "{doc}/{xobject}/{index}.{ext}".parse().unwrap();
+        let path = template.render(&fields).unwrap();
+        assert!(
+            path.components()
+                .all(|c| matches!(c, std::path::Component::Normal(_))),
+            "{name} rendered as {}",
+            path.display()
+        );
+        assert!(path.starts_with("_"));
+    }
+
+    // Text next to an empty value can still form `..`
+    dots.name = Some(String::new());
+    let fields = NameFields {
+        image: &dots,
+        ..fields
+    };
+    let template: NameTemplate = "..{xobject}/{index}".parse().unwrap();
+    assert!(matches!(
+        template.render(&fields),
+        Err(VortexError::InvalidNameTemplate(_))
+    ));
+}
+
+#[test]
+fn overwrite_policies() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("image.png");
+
+    let written = write_atomic(&path, b"first", OverwritePolicy::Fail).unwrap();
+    assert_eq!(written, Some(path.clone()));
+
+    let err = write_atomic(&path, b"second", OverwritePolicy::Fail).unwrap_err();
+    assert!(matches!(err, VortexError::Io(e) if e.kind() == std::io::ErrorKind::AlreadyExists));
+
+    let skipped = write_atomic(&path, b"second", OverwritePolicy::SkipExisting).unwrap();
+    assert_eq!(skipped, None);
+    assert_eq!(std::fs::read(&path).unwrap(), b"first");
+
+    for n in 1..=2 {
+        let renamed = write_atomic(&path, b"second", OverwritePolicy::Rename).unwrap();
+        assert_eq!(renamed, Some(dir.path().join(format!("image-{n}.png"))));
+    }
+    assert_eq!(
+        std::fs::read(dir.path().join("image-2.png")).unwrap(),
+        b"second"
+    );
+
+    write_atomic(&path, b"third", OverwritePolicy::Overwrite).unwrap();
+    assert_eq!(std::fs::read(&path).unwrap(), b"third");
+
+    // No temporary files are left behind
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 3);
+}
+
+#[test]
+fn dedupe_extracts_images_once() {
+    for (_, bytes) in SAMPLES {
+        for dedupe in [Dedupe::Reference, Dedupe::Content] {
+            let options = ExtractOptions {
+                dedupe,
+                ..Default::default()
+            };
+
+            let (images, report) =
+                extract_images_with_report(Method::Bytes(bytes), &options).unwrap();
+
+            for duplicate in &report.duplicates {
+                assert_eq!(duplicate.pages[0], duplicate.page);
+                assert!(duplicate.pages.windows(2).all(|w| w[0] < w[1]));
+                assert!(images.iter().any(|img| img.page == Some(duplicate.page)
+                    && img.page_index == duplicate.page_index));
+            }
+
+            let parallel = par_extract_images(Method::Bytes(bytes), &options).unwrap();
+            assert_eq!(parallel.len(), images.len());
+            for (a, b) in parallel.iter().zip(&images) {
+                assert_eq!((a.page, a.page_index), (b.page, b.page_index));
+            }
+        }
+    }
+}
+
+#[test]
+fn dedupe_modes() {
+    // Im1 is on both pages, Im2 is another object with the same pixels as Im1
+    let mut objects = page_tree(2);
+    objects.extend([
+        page("/XObject << /Im1 7 0 R /Im3 9 0 R >>", 5),
+        page("/XObject << /Im1 7 0 R /Im2 8 0 R >>", 6),
+        stream("", b"/Im1 Do /Im3 Do"),
+        stream("", b"/Im1 Do /Im2 Do"),
+        gray_image(&[0, 64, 128, 255]),
+        gray_image(&[0, 64, 128, 255]),
+        gray_image(&[255, 128, 64, 0]),
+    ]);
+    let pdf = build_pdf(&objects);
+
+    // Pages share a chunk of the parallel walk
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(4)
+        .build()
+        .unwrap();
+
+    let extract = |pdf: &[u8], dedupe| {
+        let options = ExtractOptions {
+            dedupe,
+            ..Default::default()
+        };
+        let (images, report) = extract_images_with_report(Method::Bytes(pdf), &options).unwrap();
+
+        let parallel = pool
+            .install(|| par_for_each_image(Method::Bytes(pdf), &options, |_, _| Ok(())))
+            .unwrap();
+        assert_eq!(parallel.extracted, images.len());
+        assert_eq!(parallel.duplicates, report.duplicates);
+
+        let origins = images
+            .iter()
+            .map(|img| (img.page.unwrap(), img.object_id.unwrap()))
+            .collect::<Vec<_>>();
+        (origins, report)
+    };
+
+    let (origins, report) = extract(&pdf, Dedupe::Off);
+    assert_eq!(origins, [(1, 7), (1, 9), (2, 7), (2, 8)]);
+    assert!(report.duplicates.is_empty());
+    assert_eq!(report.pages_of(1, 0), [1]);
+
+    let (origins, report) = extract(&pdf, Dedupe::Reference);
+    assert_eq!(origins, [(1, 7), (1, 9), (2, 8)]);
+    assert_eq!(report.duplicates.len(), 1);
+    assert_eq!(report.pages_of(1, 0), [1, 2]);
+    assert_eq!(report.pages_of(1, 1), [1]);
+    assert_eq!(report.pages_of(2, 1), [2]);
+
+    let (origins, report) = extract(&pdf, Dedupe::Content);
+    assert_eq!(origins, [(1, 7), (1, 9)]);
+    assert_eq!(report.duplicates.len(), 1);
+    assert_eq!(report.pages_of(1, 0), [1, 2]);
+    assert_eq!(report.pages_of(1, 1), [1]);
+
+    // Im2 has the same pixels as Im1 and is used on pages 2 and 3, both uses are copies
+    // of the image on page 1
+    let mut objects = page_tree(3);
+    objects.extend([
+        page("/XObject << /Im1 9 0 R >>", 6),
+        page("/XObject << /Im2 10 0 R >>", 7),
+        page("/XObject << /Im2 10 0 R >>", 8),
+        stream("", b"/Im1 Do"),
+        stream("", b"/Im2 Do"),
+        stream("", b"/Im2 Do"),
+        gray_image(&[0, 64, 128, 255]),
+        gray_image(&[0, 64, 128, 255]),
+    ]);
+    let pdf = build_pdf(&objects);
+
+    let (origins, report) = extract(&pdf, Dedupe::Reference);
+    assert_eq!(origins, [(1, 9), (2, 10)]);
+    assert_eq!(report.pages_of(1, 0), [1]);
+    assert_eq!(report.pages_of(2, 0), [2, 3]);
+
+    let (origins, report) = extract(&pdf, Dedupe::Content);
+    assert_eq!(origins, [(1, 9)]);
+    assert_eq!(report.duplicates.len(), 1);
+    assert_eq!(report.pages_of(1, 0), [1, 2, 3]);
+}
+
+#[test]
+fn inline_images() {
+    let mut contents = b"q 2 0 0 2 0 0 cm BI /Width 2 /Height 2 /ColorSpace /DeviceGray \
+        /BitsPerComponent 8 ID "
+        .to_vec();
+    contents.extend([0, 64, 128, 255]);
+    contents.extend(b" EI Q");
+
+    let mut objects = page_tree(1);
+    objects.extend([page("", 4), stream("", &contents)]);
+    let pdf = build_pdf(&objects);
+
+    let images = extract_images(Method::Bytes(&pdf)).unwrap();
+
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].name, None);
+    assert_eq!(images[0].object_id, None);
+    assert_eq!(images[0].image_dict.width, 2);
+    assert_eq!(&images[0][..], &[0, 64, 128, 255]);
+}
+
+#[test]
+fn broken_content_stream_keeps_xobject_images() {
+    let mut objects = page_tree(1);
+    objects.extend([
+        page("/XObject << /Im1 5 0 R >>", 4),
+        stream("", b"/Im1 Do (unterminated"),
+        gray_image(&[0, 64, 128, 255]),
+    ]);
+    let pdf = build_pdf(&objects);
+
+    let images = extract_images(Method::Bytes(&pdf)).unwrap();
+
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].object_id, Some(5));
+}
+
+#[test]
+fn nested_form_images() {
+    // The form draws the image and itself, the recursion has to stop at the second visit
+    let mut objects = page_tree(1);
+    objects.extend([
+        page("/XObject << /Fm1 4 0 R >>", 6),
+        stream(
+            "/Type /XObject /Subtype /Form /BBox [0 0 10 10] \
+             /Resources << /XObject << /Im1 5 0 R /Fm1 4 0 R >> >>",
+            b"/Im1 Do /Fm1 Do",
+        ),
+        gray_image(&[0, 64, 128, 255]),
+        stream("", b"q /Fm1 Do Q"),
+    ]);
+    let pdf = build_pdf(&objects);
+
+    let images = extract_images(Method::Bytes(&pdf)).unwrap();
+
+    assert_eq!(images.len(), 1);
+    assert_eq!(images[0].name.as_deref(), Some("Im1"));
+    assert_eq!(images[0].object_id, Some(5));
+    assert_eq!(&images[0][..], &[0, 64, 128, 255]);
+}
+
+#[test]
+fn encoders_round_trip() {
+    let pdf = image_pdf(gray_image(&[0, 64, 128, 255]));
+    let img = &extract_images(Method::Bytes(&pdf)).unwrap()[0];
+
+    let encode = |format: &str| {
+        let mut out = std::io::Cursor::new(vec![]);
+        create_output_writer(img, format.parse().unwrap())
+            .write_to(&mut out)
+            .unwrap();
+        out.into_inner()
+    };
+
+    for format in ["png", "webp:lossless", "tiff", "tiff:deflate", "bmp", "qoi"] {
+        let decoded = image::load_from_memory(&encode(format)).unwrap();
+        assert_eq!(decoded.to_luma8().into_raw(), [0, 64, 128, 255], "{format}");
+    }
+
+    // Lossy and palette formats only keep the dimensions for sure
+    for format in ["jpeg", "webp", "gif"] {
+        let decoded = image::load_from_memory(&encode(format)).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (2, 2), "{format}");
+    }
+
+    // `image` has no AVIF decoder without dav1d, check the container instead
+    #[cfg(feature = "avif")]
+    assert_eq!(&encode("avif")[4..12], b"ftypavif");
+
+    #[cfg(not(feature = "avif"))]
+    assert!(matches!(
+        "avif".parse::<ImageFormat>(),
+        Err(VortexError::InvalidFormat(_))
+    ));
+}
+
+#[test]
+fn write_errors_name_the_image() {
+    // Two samples for a 2x2 image only fail once converted by the writer
+    let pdf = image_pdf(stream(
+        "/Type /XObject /Subtype /Image /Width 2 /Height 2 /ColorSpace /DeviceGray \
+         /BitsPerComponent 8",
+        &[0, 255],
+    ));
+
+    let err = par_for_each_image(Method::Bytes(&pdf), &ExtractOptions::default(), |_, img| {
+        create_output_writer(&img, ImageFormat::Png(PngCompression::Fast))
+            .write_to(std::io::Cursor::new(vec![]))
+    })
+    .unwrap_err();
+
+    match err {
+        VortexError::Write {
+            page: Some(1),
+            object_id: Some(5),
+            ref source,
+        } => assert!(matches!(**source, VortexError::InvalidImage(_))),
+        e => panic!("unexpected error {e:?}"),
+    }
+    assert_eq!(err.to_string(), "Failed to write image 5 on page 1");
+}